@@ -0,0 +1,95 @@
+//! Procedural macros used by the `codectrl` crate. These are re-exported and
+//! used by its logging macros, and are not meant to be used directly.
+
+use proc_macro::{Delimiter, Group, Literal, Span, TokenStream, TokenTree};
+use std::fs;
+
+// The number of lines on each side of the callsite that are embedded. Snippets
+// with a larger surround are cut down to this.
+const EMBEDDED_SURROUND: usize = 5;
+
+/// Expands to a `&'static [(u32, &'static str)]` of the line numbers and source
+/// lines around the first token of its input, read from the source file while
+/// the macro is expanded. This allows a binary compiled without debug info to
+/// still send a code snippet.
+///
+/// Expands to an empty slice if the source file can't be read.
+///
+/// The file isn't registered as an input of the build by the macro itself, as
+/// `proc_macro::tracked_path` isn't stable yet. The file that the macro is
+/// called from is an input of the crate that calls it, so its lines are
+/// embedded again whenever it changes, but a callsite in a file that Cargo
+/// doesn't track, i.e. one generated by a build script that doesn't declare
+/// it, can keep stale lines until the crate is rebuilt.
+#[proc_macro]
+pub fn source_lines(input: TokenStream) -> TokenStream {
+    let span = first_span(input).unwrap_or_else(Span::call_site);
+
+    let lines = span
+        .local_file()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|source| {
+            let line = span.line();
+            let first = line.saturating_sub(EMBEDDED_SURROUND).max(1);
+
+            source
+                .lines()
+                .enumerate()
+                .map(|(n, text)| (n + 1, text))
+                .skip(first - 1)
+                .take(line + EMBEDDED_SURROUND + 1 - first)
+                .map(|(n, text)| format!("({n}u32, {})", Literal::string(text)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .unwrap_or_default();
+
+    format!("{{ const LINES: &[(u32, &str)] = &[{lines}]; LINES }}")
+        .parse()
+        .expect("source lines are valid tokens")
+}
+
+/// Expands a string literal to a `String` formatted with `format!`, so that it
+/// can capture variables inline, i.e. `"user {id}"`. Any other literal, i.e.
+/// `42`, expands to itself, so that it is logged as its `Debug` value.
+#[proc_macro]
+pub fn format_literal(input: TokenStream) -> TokenStream {
+    let literal = match first_token(input) {
+        Some(TokenTree::Literal(literal)) => literal,
+        Some(token) => return token.into(),
+        None => return TokenStream::new(),
+    };
+
+    let text = literal.to_string();
+
+    if !(text.starts_with('"') || text.starts_with("r\"") || text.starts_with("r#")) {
+        return TokenTree::Literal(literal).into();
+    }
+
+    // The literal keeps its span, so that the variables it captures are
+    // resolved at the callsite.
+    let mut format: TokenStream = "::std::format!".parse().expect("valid tokens");
+    format.extend([TokenTree::Group(Group::new(
+        Delimiter::Parenthesis,
+        TokenTree::Literal(literal).into(),
+    ))]);
+
+    format
+}
+
+// The first token, looking inside of the invisible groups that `macro_rules!`
+// fragments are passed in.
+fn first_token(input: TokenStream) -> Option<TokenTree> {
+    match input.into_iter().next()? {
+        TokenTree::Group(group) if group.delimiter() == Delimiter::None =>
+            first_token(group.stream()),
+        token => Some(token),
+    }
+}
+
+// The span of the first token, looking inside of the invisible groups that
+// `macro_rules!` fragments are passed in, which span the fragment in the macro
+// definition rather than at the callsite.
+fn first_span(input: TokenStream) -> Option<Span> {
+    first_token(input).map(|token| token.span())
+}
@@ -0,0 +1,464 @@
+use crate::{
+    diagnostics, runtime, BatchReport, Field, Handle, Level, Log, LogBatch, Logger,
+    LoggerResult, Message,
+};
+use serde::Serialize;
+use std::{
+    fmt::Debug,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak},
+    time::{Duration, Instant},
+};
+
+/// A batch of logs that sends itself once it reaches a configured number of
+/// logs, size in bytes or age of its oldest log, and when it is dropped.
+/// Created with [`LogBatch::auto_flush`].
+///
+/// Unlike [`LogBatch`], logs are added through `&mut self`, so that it can be
+/// passed to other functions and used inside of loops.
+///
+/// ```no_run
+/// use codectrl::{Logger, LoggerError};
+/// use std::time::Duration;
+///
+/// fn process(items: &[u32]) -> Result<(), LoggerError> {
+///     let mut batch = Logger::start_batch()
+///         .auto_flush()
+///         .max_logs(100)
+///         .max_age(Duration::from_secs(5));
+///
+///     for item in items {
+///         batch.add_log(item, None)?;
+///     }
+///
+///     // Anything that is left is sent when `batch` is dropped, even if this
+///     // function returns early.
+///     Ok(())
+/// }
+/// ```
+///
+/// Once the oldest log is `max_age` old, the batch is sent by a task on the
+/// executor that is shared by the crate, even if no more logs are added. As
+/// there is no caller to return an error to, it is sent to the
+/// [diagnostics](crate::set_diagnostics) handler instead, and the logs that
+/// failed are kept and sent again with the next flush.
+///
+/// When the batch is dropped inside of an async runtime, where it can't block
+/// until the batch is sent, it is sent by a task on the shared executor
+/// instead, and errors are sent to the diagnostics handler.
+///
+/// A log that would take the batch over
+/// [`max_batch_bytes`](crate::Limits::max_batch_bytes) causes the batch to be
+/// sent before the log is added, rather than the log being truncated or
+/// dropped.
+pub struct AutoFlushBatch<'a> {
+    shared: Arc<Shared>,
+    tokio_runtime: Option<&'a Handle>,
+    max_logs: Option<usize>,
+    max_bytes: Option<usize>,
+    max_age: Option<Duration>,
+}
+
+// The batch, which is shared with the task that sends it once its oldest log is
+// `max_age` old.
+struct Shared {
+    state: Mutex<State>,
+    // Notified when a flush has finished.
+    flushed: Condvar,
+}
+
+struct State {
+    batch: LogBatch<'static>,
+    oldest: Option<Instant>,
+    // Whether the logs have been taken out of the batch to be sent. Only one
+    // flush runs at a time, so that the batch is sent in order.
+    flushing: bool,
+    // Whether the `AutoFlushBatch` was dropped without being sent, so that the
+    // flush that is running sends what is left once it has finished.
+    dropped: bool,
+}
+
+impl<'a> AutoFlushBatch<'a> {
+    pub(crate) fn new(batch: LogBatch<'a>) -> Self {
+        let (batch, tokio_runtime) = batch.into_static();
+
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    batch,
+                    oldest: None,
+                    flushing: false,
+                    dropped: false,
+                }),
+                flushed: Condvar::new(),
+            }),
+            tokio_runtime,
+            max_logs: None,
+            max_bytes: None,
+            max_age: None,
+        }
+    }
+
+    /// Sends the batch once it holds `max_logs` logs.
+    pub fn max_logs(mut self, max_logs: usize) -> Self {
+        self.max_logs = Some(max_logs);
+        self
+    }
+
+    /// Sends the batch once the logs it holds reach `max_bytes` in size.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Sends the batch once its oldest log is older than `max_age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Auto-flushing equivelent of [`LogBatch::add_log`]. Returns an error if
+    /// adding the log caused the batch to be sent, and sending it failed.
+    #[track_caller]
+    pub fn add_log<T: Debug>(
+        &mut self,
+        message: T,
+        surround: Option<u32>,
+    ) -> LoggerResult<()> {
+        self.add(Level::Info, || Message::debug(&message), surround)
+    }
+
+    /// Auto-flushing equivelent of [`LogBatch::add_log_at`].
+    #[track_caller]
+    pub fn add_log_at<T: Debug>(
+        &mut self,
+        level: Level,
+        message: T,
+        surround: Option<u32>,
+    ) -> LoggerResult<()> {
+        self.add(level, || Message::debug(&message), surround)
+    }
+
+    /// Auto-flushing equivelent of [`LogBatch::add_log_with`].
+    #[track_caller]
+    pub fn add_log_with<T: Debug>(
+        &mut self,
+        fields: impl IntoIterator<Item = Field>,
+        message: T,
+        surround: Option<u32>,
+    ) -> LoggerResult<()> {
+        self.add(
+            Level::Info,
+            || Message::debug(&message).with_fields(fields),
+            surround,
+        )
+    }
+
+    /// Auto-flushing equivelent of [`LogBatch::add_log_serialized`].
+    #[track_caller]
+    pub fn add_log_serialized<T: Serialize>(
+        &mut self,
+        message: T,
+        surround: Option<u32>,
+    ) -> LoggerResult<()> {
+        self.add(Level::Info, || Message::serialized(&message), surround)
+    }
+
+    /// The number of logs waiting to be sent.
+    pub fn len(&self) -> usize { self.shared.lock().batch.len() }
+
+    /// Whether there are no logs waiting to be sent.
+    pub fn is_empty(&self) -> bool { self.shared.lock().batch.is_empty() }
+
+    /// Sends the batch if any of the configured limits have been reached.
+    /// Returns whether the batch was sent.
+    pub fn flush_if_due(&mut self) -> LoggerResult<bool> {
+        if !self.is_due(&self.shared.lock()) {
+            return Ok(false);
+        }
+
+        self.flush()?;
+
+        Ok(true)
+    }
+
+    /// Sends every log in the batch, regardless of the configured limits. Does
+    /// nothing if the batch is empty. Waits for the batch to be sent first if
+    /// it is already being sent because of its age.
+    ///
+    /// If sending fails, the logs are kept in the batch so that they are sent
+    /// with the next flush.
+    pub fn flush(&mut self) -> LoggerResult<()> {
+        let Some((mut logger, flushing)) = self.shared.take(self.tokio_runtime) else {
+            return Ok(());
+        };
+
+        let report = logger.send_batch_reported();
+
+        flushing.restore(logger, self.max_age);
+
+        report?.into_result()
+    }
+
+    /// An async version of [`Self::flush_if_due`], see
+    /// [`Logger::send_batch_async`](crate::Logger::send_batch_async).
+    pub async fn flush_if_due_async(&mut self) -> LoggerResult<bool> {
+        if !self.is_due(&self.shared.lock()) {
+            return Ok(false);
+        }
+
+        self.flush_async().await?;
+
+        Ok(true)
+    }
+
+    /// An async version of [`Self::flush`], see
+    /// [`Logger::send_batch_async`](crate::Logger::send_batch_async).
+    pub async fn flush_async(&mut self) -> LoggerResult<()> {
+        let Some((mut logger, flushing)) = self.shared.take(self.tokio_runtime) else {
+            return Ok(());
+        };
+
+        let report = logger.send_batch_reported_async().await;
+
+        flushing.restore(logger, self.max_age);
+
+        report?.into_result()
+    }
+
+    #[track_caller]
+    fn add(
+        &mut self,
+        level: Level,
+        message: impl FnOnce() -> Message,
+        surround: Option<u32>,
+    ) -> LoggerResult<()> {
+        let mut state = self.shared.lock();
+        let Some(log) = state
+            .batch
+            .create_message(level, None, true, message, surround)
+        else {
+            return Ok(());
+        };
+
+        if !state.batch.fits(&log) && !state.batch.is_empty() {
+            drop(state);
+
+            // The log is added even if sending failed, in which case it may be
+            // truncated or dropped to stay within the limit.
+            let result = self.flush();
+
+            self.push(log);
+
+            return result;
+        }
+
+        drop(state);
+        self.push(log);
+
+        self.flush_if_due().map(|_| ())
+    }
+
+    fn push(&mut self, log: Log) {
+        let mut state = self.shared.lock();
+
+        state.batch.push_within_limit(log);
+
+        if !state.batch.is_empty() && state.oldest.is_none() {
+            let oldest = Instant::now();
+
+            state.oldest = Some(oldest);
+
+            if let Some(max_age) = self.max_age {
+                self.shared.flush_at(oldest, max_age);
+            }
+        }
+    }
+
+    fn is_due(&self, state: &State) -> bool {
+        if state.batch.is_empty() {
+            return false;
+        }
+
+        self.max_logs
+            .is_some_and(|max_logs| state.batch.len() >= max_logs)
+            || self
+                .max_bytes
+                .is_some_and(|max_bytes| state.batch.batch_bytes >= max_bytes)
+            || self
+                .max_age
+                .zip(state.oldest)
+                .is_some_and(|(max_age, oldest)| oldest.elapsed() >= max_age)
+    }
+}
+
+impl Drop for AutoFlushBatch<'_> {
+    fn drop(&mut self) {
+        // Blocking inside of an async runtime would panic, or hold it up, so
+        // the batch is sent by a task instead.
+        if runtime::in_async_context() {
+            self.shared.flush_dropped();
+            return;
+        }
+
+        // There is no way to report an error from `drop`, so the logs that
+        // could not be sent are lost.
+        let _ = self.flush();
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Takes the logs out of the batch to send them on `tokio_runtime`, once a
+    // flush that is already running has finished. Returns `None` if there is
+    // nothing to send.
+    fn take<'b>(
+        self: &Arc<Self>,
+        tokio_runtime: Option<&'b Handle>,
+    ) -> Option<(Logger<'b>, Flushing)> {
+        let mut state = self.lock();
+
+        while state.flushing {
+            state = self
+                .flushed
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+
+        self.start_flush(state, tokio_runtime)
+    }
+
+    // `take`, for the task that is started by `flush_at`, which must not block
+    // the executor. Returns `None` if the batch is being sent, or was sent since
+    // the log that was added at `oldest`.
+    fn take_due(
+        self: &Arc<Self>,
+        oldest: Instant,
+    ) -> Option<(Logger<'static>, Flushing)> {
+        let state = self.lock();
+
+        if state.flushing || state.oldest != Some(oldest) {
+            return None;
+        }
+
+        self.start_flush(state, None)
+    }
+
+    fn start_flush<'b>(
+        self: &Arc<Self>,
+        mut state: MutexGuard<'_, State>,
+        tokio_runtime: Option<&'b Handle>,
+    ) -> Option<(Logger<'b>, Flushing)> {
+        if state.batch.nothing_to_send() {
+            return None;
+        }
+
+        state.flushing = true;
+        state.oldest = None;
+
+        Some((
+            state.batch.take_logger_on(tokio_runtime),
+            Flushing(self.clone()),
+        ))
+    }
+
+    // Starts a task that sends the batch once the log that was added at
+    // `oldest` is `max_age` old, unless the batch was sent before then. The
+    // task doesn't keep the batch alive.
+    fn flush_at(self: &Arc<Self>, oldest: Instant, max_age: Duration) {
+        let shared = Arc::downgrade(self);
+
+        // If the executor can't be started, the batch is still sent once a log
+        // is added after `max_age`, or it is dropped.
+        let _ = runtime::detach(|| async move {
+            runtime::sleep(max_age).await;
+
+            let Some(shared) = Weak::upgrade(&shared) else {
+                return;
+            };
+
+            let Some((mut logger, flushing)) = shared.take_due(oldest) else {
+                return;
+            };
+
+            let report = logger.send_batch_reported_async().await;
+
+            flushing.restore(logger, Some(max_age));
+
+            if let Err(e) = report.and_then(BatchReport::into_result) {
+                diagnostics::warn("Could not send an auto-flushing batch", e);
+            }
+        });
+    }
+
+    // Starts a task that sends what is left in the batch once its
+    // `AutoFlushBatch` was dropped, or marks it to be sent once the flush that
+    // is running has finished. The task keeps the batch alive, and the logs
+    // that it fails to send are lost.
+    fn flush_dropped(self: &Arc<Self>) {
+        let mut state = self.lock();
+
+        state.dropped = true;
+
+        if state.flushing {
+            return;
+        }
+
+        let Some((mut logger, flushing)) = self.start_flush(state, None) else {
+            return;
+        };
+
+        let _ = runtime::detach(|| async move {
+            let report = logger.send_batch_reported_async().await;
+
+            drop(flushing);
+
+            if let Err(e) = report.and_then(BatchReport::into_result) {
+                diagnostics::warn("Could not send an auto-flushing batch", e);
+            }
+        });
+    }
+}
+
+// Marks a batch as being sent until it is dropped, which also happens if the
+// future that sends it is dropped.
+struct Flushing(Arc<Shared>);
+
+impl Flushing {
+    // Keeps the logs that `logger` failed to send, which are sent again once
+    // they are `max_age` old.
+    fn restore(self, logger: Logger<'_>, max_age: Option<Duration>) {
+        let mut state = self.0.lock();
+
+        state.batch.restore_logger(logger);
+
+        if !state.batch.is_empty() && state.oldest.is_none() {
+            let oldest = Instant::now();
+
+            state.oldest = Some(oldest);
+
+            if let Some(max_age) = max_age {
+                self.0.flush_at(oldest, max_age);
+            }
+        }
+    }
+}
+
+impl Drop for Flushing {
+    fn drop(&mut self) {
+        let dropped = {
+            let mut state = self.0.lock();
+
+            state.flushing = false;
+            state.dropped
+        };
+
+        self.0.flushed.notify_all();
+
+        if dropped {
+            self.0.flush_dropped();
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use codectrl::{Logger, LoggerError};
 
 fn main() -> anyhow::Result<()> {
+    // `LoggerError` is large, see the same allow in the library.
+    #[allow(clippy::result_large_err)]
     fn inner() -> Result<(), LoggerError> {
         Logger::log("Hello, world!", None, None, None, None)
     }
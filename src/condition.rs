@@ -0,0 +1,131 @@
+//! Conditions for [`Logger::log_if`] and [`LogBatch::add_log_if`], and
+//! built-in conditions that can be combined with [`Condition::and`],
+//! [`Condition::or`] and [`Condition::not`].
+//!
+//! ```no_run
+//! use codectrl::{condition, Condition, Logger};
+//!
+//! # fn main() -> Result<(), codectrl::LoggerError> {
+//! let retries = 3;
+//!
+//! Logger::log_if(
+//!     condition::env_present("CODECTRL_DEBUG").and(move || retries > 2),
+//!     "Retrying again",
+//!     None,
+//!     None,
+//!     None,
+//!     None,
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Logger::log_if`]: crate::Logger::log_if
+//! [`LogBatch::add_log_if`]: crate::LogBatch::add_log_if
+
+use crate::rate_limit::Callsite;
+use std::{
+    collections::HashMap,
+    env,
+    panic::Location,
+    sync::{Mutex, OnceLock, PoisonError},
+    time::SystemTime,
+};
+
+/// Decides whether a log is sent. Implemented for any `FnOnce() -> bool`,
+/// including `fn() -> bool` and `Box<dyn FnOnce() -> bool>`.
+///
+/// A condition is only checked if the level of the log is enabled.
+pub trait Condition {
+    /// Whether the log should be sent.
+    fn check(self) -> bool;
+
+    /// Only true if both `self` and `other` are. `other` is not checked if
+    /// `self` is false.
+    fn and(self, other: impl Condition) -> impl Condition
+    where
+        Self: Sized,
+    {
+        move || self.check() && other.check()
+    }
+
+    /// True if either `self` or `other` is. `other` is not checked if `self`
+    /// is true.
+    fn or(self, other: impl Condition) -> impl Condition
+    where
+        Self: Sized,
+    {
+        move || self.check() || other.check()
+    }
+
+    /// True if `self` is false.
+    fn not(self) -> impl Condition
+    where
+        Self: Sized,
+    {
+        move || !self.check()
+    }
+}
+
+impl<F: FnOnce() -> bool> Condition for F {
+    fn check(self) -> bool { self() }
+}
+
+/// True if the environment variable `name` is present.
+pub fn env_present(name: impl Into<String>) -> impl Condition {
+    let name = name.into();
+
+    move || env::var_os(name).is_some()
+}
+
+/// True if the environment variable `name` is equal to `value`.
+pub fn env_equals(name: impl Into<String>, value: impl Into<String>) -> impl Condition {
+    let (name, value) = (name.into(), value.into());
+
+    move || env::var(name).is_ok_and(|var| var == value)
+}
+
+/// True with the given probability, between `0.0` and `1.0`.
+pub fn sample(probability: f64) -> impl Condition {
+    move || rand::random::<f64>() < probability
+}
+
+/// True if the current time is at or after `start` and before `end`.
+pub fn within(start: SystemTime, end: SystemTime) -> impl Condition {
+    move || (start..end).contains(&SystemTime::now())
+}
+
+/// True only the `n`th time that it is checked at this callsite, counting from
+/// 1. The callsite is the file, line and column of the call to `nth`.
+#[track_caller]
+pub fn nth(n: u64) -> impl Condition {
+    let callsite = Callsite::from(Location::caller());
+
+    move || {
+        static OCCURENCES: OnceLock<Mutex<HashMap<Callsite, u64>>> = OnceLock::new();
+
+        let mut occurences = OCCURENCES
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let occurence = occurences.entry(callsite).or_default();
+
+        *occurence += 1;
+        *occurence == n
+    }
+}
+
+/// A [`Condition`] that is true if the given cargo feature is enabled in the
+/// crate that uses this macro.
+///
+/// ```
+/// use codectrl::Condition;
+///
+/// assert!(!codectrl::feature_enabled!("verbose-logs").check());
+/// ```
+#[macro_export]
+macro_rules! feature_enabled {
+    ($feature:literal) => {
+        || ::std::cfg!(feature = $feature)
+    };
+}
@@ -0,0 +1,31 @@
+// Stands in for the types of `codectrl-protobuf-bindings` that logs are made
+// of when the `logging` feature is disabled, so that the bindings, and tonic
+// and prost with them, aren't compiled. Only the `disabled` feature builds
+// without `logging`, so no log is ever created from these.
+
+use std::collections::BTreeMap;
+
+/// A log, as sent to the server. See `codectrl_protobuf_bindings::data::Log`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Log {
+    pub uuid: String,
+    pub stack: Vec<BacktraceData>,
+    pub line_number: u32,
+    pub file_name: String,
+    pub code_snippet: BTreeMap<u32, String>,
+    pub message: String,
+    pub message_type: String,
+    pub address: String,
+    pub warnings: Vec<String>,
+    pub language: String,
+}
+
+/// A frame of the stack trace of a [`Log`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BacktraceData {
+    pub name: String,
+    pub file_path: String,
+    pub line_number: u32,
+    pub column_number: u32,
+    pub code: String,
+}
@@ -0,0 +1,105 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Debug, Display},
+    sync::{Arc, Mutex, OnceLock, PoisonError, RwLock},
+};
+
+/// Where the warnings of the logger itself are sent, such as a log that could
+/// not be sent from a [`TimerGuard`](crate::TimerGuard), where there is no
+/// caller to return the error to. Each warning is only shown once.
+///
+/// Set it with [`set_diagnostics`]. Defaults to [`Diagnostics::Stderr`].
+///
+/// ```
+/// use codectrl::{set_diagnostics, Diagnostics};
+/// use std::sync::Arc;
+///
+/// // Keep stdout and stderr clean for a tool whose output is parsed.
+/// set_diagnostics(Diagnostics::Silent);
+///
+/// set_diagnostics(Diagnostics::Custom(Arc::new(|warning| {
+///     // Show the warning in the UI of the program instead.
+///     let _ = warning;
+/// })));
+/// ```
+#[derive(Clone, Default)]
+pub enum Diagnostics {
+    /// Warnings are dropped.
+    Silent,
+    /// Warnings are written to stderr.
+    #[default]
+    Stderr,
+    /// Warnings are forwarded to the [`log`] crate at the warn level, with the
+    /// target `codectrl`.
+    Log,
+    /// Warnings are passed to the given function.
+    Custom(Arc<dyn Fn(&str) + Send + Sync>),
+}
+
+impl Debug for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Silent => write!(f, "Silent"),
+            Self::Stderr => write!(f, "Stderr"),
+            Self::Log => write!(f, "Log"),
+            Self::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+fn global() -> &'static RwLock<Diagnostics> {
+    static DIAGNOSTICS: OnceLock<RwLock<Diagnostics>> = OnceLock::new();
+
+    DIAGNOSTICS.get_or_init(Default::default)
+}
+
+/// Sets where the warnings of the logger itself are sent.
+pub fn set_diagnostics(diagnostics: Diagnostics) {
+    *global().write().unwrap_or_else(PoisonError::into_inner) = diagnostics;
+}
+
+// Sends a warning of the given `kind`, followed by its `details`, such as the
+// error, to the diagnostics handler, unless a warning of the same kind was
+// already sent before. Only the kinds are kept, which are fixed, so that
+// warnings with different details don't pile up.
+pub(crate) fn warn(kind: &'static str, details: impl Display) {
+    show(kind, None, format!("{kind}: {details}"));
+}
+
+// `warn`, for a warning about a `subject` such as an endpoint, which is sent
+// once for each subject of the kind instead.
+#[cfg(not(feature = "disabled"))]
+pub(crate) fn warn_about(kind: &'static str, subject: String, details: impl Display) {
+    let warning = format!("{kind} ({subject}): {details}");
+
+    show(kind, Some(subject), warning);
+}
+
+// The kinds of warnings that were sent, with their subject if they have one.
+type Shown = HashSet<(&'static str, Option<String>)>;
+
+fn show(kind: &'static str, subject: Option<String>, warning: String) {
+    static SHOWN: OnceLock<Mutex<Shown>> = OnceLock::new();
+
+    if !SHOWN
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert((kind, subject))
+    {
+        return;
+    }
+
+    // The handler is cloned so that it may set the diagnostics itself.
+    let diagnostics = global()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+
+    match diagnostics {
+        Diagnostics::Silent => {},
+        Diagnostics::Stderr => eprintln!("{warning}"),
+        Diagnostics::Log => log::warn!(target: "codectrl", "{warning}"),
+        Diagnostics::Custom(handler) => handler(&warning),
+    }
+}
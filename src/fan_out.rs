@@ -0,0 +1,285 @@
+use crate::Log;
+use std::collections::VecDeque;
+
+#[cfg(not(feature = "disabled"))]
+use crate::{
+    diagnostics, limits, runtime,
+    stats::{self, Counter},
+    transport::Endpoints,
+    BatchReport, DeliveryMode, EndpointReport, Logger,
+};
+#[cfg(not(feature = "disabled"))]
+use futures_util::future;
+#[cfg(not(feature = "disabled"))]
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    mem,
+    sync::{Mutex, OnceLock, PoisonError},
+    time::Duration,
+};
+
+// An endpoint that a batch is also sent to, with the logs that it failed to
+// receive so far, which are sent to it again with the next batch. The endpoint
+// is only read when sending, which the `disabled` feature compiles out.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "disabled", allow(dead_code))]
+pub(crate) struct FanOut {
+    pub(crate) host: String,
+    pub(crate) port: String,
+    pub(crate) backlog: VecDeque<Log>,
+}
+
+impl FanOut {
+    pub(crate) fn new(host: &str, port: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            port: port.to_string(),
+            backlog: VecDeque::new(),
+        }
+    }
+}
+
+// Sends `logs` to `endpoints`, and to every endpoint in `fan_out`, each
+// over its own connection and at the same time. The first `retained` logs
+// failed to be sent to `endpoints` before and were already queued for the other
+// endpoints, so only the logs after them are added to their backlogs. Like a
+// batch, a backlog is kept within `max_bytes` by dropping the newer logs.
+//
+// Returns the logs that failed for `endpoints`, and the endpoints with the logs
+// that failed for them.
+#[cfg(not(feature = "disabled"))]
+pub(crate) async fn send(
+    endpoints: Endpoints,
+    mode: DeliveryMode,
+    logs: VecDeque<Log>,
+    retained: usize,
+    max_bytes: Option<usize>,
+    fan_out: Vec<FanOut>,
+) -> (BatchReport, VecDeque<Log>, Vec<FanOut>) {
+    let connect_timeout = endpoints.connect_timeout;
+    let sends = fan_out
+        .into_iter()
+        .map(|mut endpoint| {
+            let retried = endpoint.backlog.len();
+            let dropped = queue(&mut endpoint, logs.iter().skip(retained), max_bytes);
+
+            send_backlog(endpoint, retried, dropped, mode, connect_timeout)
+        })
+        .collect::<Vec<_>>();
+
+    // Only the backlogs have logs to send if the batch is empty.
+    let send = async {
+        match logs.is_empty() {
+            true => (BatchReport::default(), logs),
+            false => {
+                let retried = retained.min(logs.len());
+                let (report, failed) = Logger::_send_batch(endpoints, mode, logs).await;
+
+                count_retries(&report, retried);
+
+                (report, failed)
+            },
+        }
+    };
+
+    let ((mut report, failed), fan_out) =
+        future::join(send, future::join_all(sends)).await;
+
+    let fan_out = fan_out
+        .into_iter()
+        .map(|(endpoint, endpoint_report)| {
+            report.fan_out.push(endpoint_report);
+
+            endpoint
+        })
+        .collect();
+
+    (report, failed, fan_out)
+}
+
+// The endpoints that single logs are fanned out to, with their backlogs, for
+// each list of endpoints that the logs are sent to.
+#[cfg(not(feature = "disabled"))]
+type Backlogs = HashMap<Endpoints, Vec<FanOut>>;
+
+#[cfg(not(feature = "disabled"))]
+fn backlogs() -> &'static Mutex<Backlogs> {
+    static BACKLOGS: OnceLock<Mutex<Backlogs>> = OnceLock::new();
+
+    BACKLOGS.get_or_init(Default::default)
+}
+
+// Sends a single `log`, which is sent to `endpoints` separately, to the
+// endpoints that they fan out to, as a task of its own, so that an endpoint
+// that is down doesn't hold up the log.
+#[cfg(not(feature = "disabled"))]
+pub(crate) fn spawn_log(endpoints: &Endpoints, log: &Log) {
+    if endpoints.fan_out.is_empty() {
+        return;
+    }
+
+    let (endpoints, log) = (endpoints.clone(), log.clone());
+
+    // The log is still sent to `endpoints` if the executor can't be started.
+    let _ = runtime::detach(|| send_log(endpoints, log));
+}
+
+// The logs that each endpoint failed to receive are kept in its backlog, within
+// `max_batch_bytes`, and sent to it again with the next log. There is no caller
+// to return the report of an endpoint to, so its errors are sent to the
+// diagnostics handler instead.
+#[cfg(not(feature = "disabled"))]
+async fn send_log(endpoints: Endpoints, log: Log) {
+    let fan_out = backlogs()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&endpoints)
+        .unwrap_or_else(|| {
+            endpoints
+                .fan_out
+                .iter()
+                .map(|(host, port)| FanOut::new(host, port))
+                .collect()
+        });
+
+    let max_bytes = limits::limits().max_batch_bytes;
+    let connect_timeout = endpoints.connect_timeout;
+    let sends = fan_out.into_iter().map(|mut endpoint| {
+        let retried = endpoint.backlog.len();
+        let dropped = queue(&mut endpoint, [&log], max_bytes);
+
+        send_backlog(
+            endpoint,
+            retried,
+            dropped,
+            DeliveryMode::Stream,
+            connect_timeout,
+        )
+    });
+
+    let mut fan_out = future::join_all(sends)
+        .await
+        .into_iter()
+        .map(|(endpoint, report)| {
+            warn_failed(report);
+            endpoint
+        })
+        .collect::<Vec<_>>();
+
+    let mut backlogs = backlogs().lock().unwrap_or_else(PoisonError::into_inner);
+
+    match backlogs.entry(endpoints) {
+        // A log that was fanned out at the same time left its backlogs, which
+        // are kept after these.
+        Entry::Occupied(mut entry) => {
+            for (endpoint, newer) in fan_out.iter_mut().zip(entry.get_mut()) {
+                endpoint.backlog.append(&mut newer.backlog);
+            }
+
+            entry.insert(fan_out);
+        },
+        Entry::Vacant(entry) => {
+            entry.insert(fan_out);
+        },
+    }
+}
+
+#[cfg(not(feature = "disabled"))]
+fn warn_failed(report: EndpointReport) {
+    let endpoint = format!("{}:{}", report.host, report.port);
+    let dropped = report.dropped;
+
+    if let Err(e) = report.report.into_result() {
+        diagnostics::warn_about("Could not fan out a log", endpoint.clone(), e);
+    }
+
+    if dropped > 0 {
+        diagnostics::warn_about(
+            "Dropped logs that were fanned out",
+            endpoint,
+            format!("{dropped} did not fit into the backlog of the endpoint"),
+        );
+    }
+}
+
+// Adds `logs` to the backlog of `endpoint`, as far as they fit within
+// `max_bytes`, and returns how many didn't.
+#[cfg(not(feature = "disabled"))]
+fn queue<'a>(
+    endpoint: &mut FanOut,
+    logs: impl IntoIterator<Item = &'a Log>,
+    max_bytes: Option<usize>,
+) -> u64 {
+    let mut bytes = endpoint.backlog.iter().map(limits::log_size).sum::<usize>();
+    let mut logs = logs.into_iter();
+    let mut dropped = 0;
+
+    while let Some(log) = logs.next() {
+        bytes += limits::log_size(log);
+
+        if max_bytes.is_some_and(|max_bytes| bytes > max_bytes) {
+            dropped = 1 + logs.count() as u64;
+            break;
+        }
+
+        endpoint.backlog.push_back(log.clone());
+    }
+
+    stats::add(Counter::Dropped, dropped);
+
+    dropped
+}
+
+// Sends the backlog of `endpoint` over a connection of its own, unless it is
+// empty, and keeps the logs that failed in it. The first `retried` logs of the
+// backlog failed to be sent to it before.
+#[cfg(not(feature = "disabled"))]
+async fn send_backlog(
+    mut endpoint: FanOut,
+    retried: usize,
+    dropped: u64,
+    mode: DeliveryMode,
+    connect_timeout: Duration,
+) -> (FanOut, EndpointReport) {
+    let report = match endpoint.backlog.is_empty() {
+        true => BatchReport::default(),
+        false => {
+            let (report, failed) = Logger::_send_batch(
+                Endpoints::new(&endpoint.host, &endpoint.port)
+                    .connect_timeout(connect_timeout),
+                mode,
+                mem::take(&mut endpoint.backlog),
+            )
+            .await;
+
+            count_retries(&report, retried);
+            endpoint.backlog = failed;
+
+            report
+        },
+    };
+
+    let report = EndpointReport {
+        host: endpoint.host.clone(),
+        port: endpoint.port.clone(),
+        report,
+        dropped,
+    };
+
+    (endpoint, report)
+}
+
+// Counts the first `retried` logs that `report` is for as retried, as they
+// failed to be sent before, and the other logs that failed as spooled, so that
+// each log is only spooled once, however often it is retried.
+#[cfg(not(feature = "disabled"))]
+fn count_retries(report: &BatchReport, retried: usize) {
+    let spooled = report
+        .failed
+        .iter()
+        .filter(|failed| failed.index >= retried)
+        .count();
+
+    stats::add(Counter::Retried, retried as u64);
+    stats::add(Counter::Spooled, spooled as u64);
+}
@@ -0,0 +1,91 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt::Debug;
+
+/// The prefix of the last line of a [`Log`]'s `message` when it carries
+/// [`Field`]s. The rest of that line is a JSON object of the fields, i.e.:
+///
+/// ```text
+/// "Request failed"
+///
+/// codectrl-fields: {"attempt":"3","ids":[1,2],"request_id":"\"a1b2\""}
+/// ```
+///
+/// Fields created with [`Field::new`] are sent as the JSON string of their
+/// [`Debug`] representation, while fields created with [`Field::serialized`]
+/// are sent as their JSON value.
+///
+/// [`Log`]: codectrl_protobuf_bindings::data::Log
+pub const FIELDS_PREFIX: &str = "codectrl-fields: ";
+
+/// A key-value pair of context that is attached to a log, i.e. a request ID or
+/// the current user, without being part of the message. Used by
+/// [`Logger::log_with`] and [`LogBatch::add_log_with`].
+///
+/// See [`fields!`] for a shorthand for creating a list of fields from [`Debug`]
+/// values.
+///
+/// [`Logger::log_with`]: crate::Logger::log_with
+/// [`LogBatch::add_log_with`]: crate::LogBatch::add_log_with
+/// [`fields!`]: crate::fields!
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub(crate) key: String,
+    pub(crate) value: Value,
+}
+
+impl Field {
+    /// Creates a field from the [`Debug`] representation of `value`.
+    pub fn new(key: impl Into<String>, value: impl Debug) -> Self {
+        Self {
+            key: key.into(),
+            value: Value::String(format!("{value:?}")),
+        }
+    }
+
+    /// Creates a field from the JSON representation of `value`. If `value`
+    /// fails to serialise, the error is sent as the value of the field instead.
+    pub fn serialized(key: impl Into<String>, value: impl Serialize) -> Self {
+        Self {
+            key: key.into(),
+            value: serde_json::to_value(value).unwrap_or_else(|e| {
+                Value::String(format!("<could not be serialised: {e}>"))
+            }),
+        }
+    }
+
+    /// The key of the field.
+    pub fn key(&self) -> &str { &self.key }
+}
+
+/// Creates an array of [`Field`]s from `key = value` pairs, using the [`Debug`]
+/// representation of each value.
+///
+/// ```
+/// # let request_id = "a1b2";
+/// let fields = codectrl::fields![request_id = request_id, attempt = 3];
+///
+/// assert_eq!(fields[1].key(), "attempt");
+/// ```
+#[macro_export]
+macro_rules! fields {
+    ($($key:ident = $value:expr),* $(,)?) => {
+        [$($crate::Field::new(stringify!($key), &$value)),*]
+    };
+}
+
+// Appends the `FIELDS_PREFIX` line to `message`, if there are any fields.
+pub(crate) fn append_fields(message: &mut String, fields: Vec<Field>) {
+    if fields.is_empty() {
+        return;
+    }
+
+    let fields = fields
+        .into_iter()
+        .map(|field| (field.key, field.value))
+        .collect::<Map<_, _>>();
+
+    message.push_str("\n\n");
+    message.push_str(FIELDS_PREFIX);
+    message.push_str(&Value::Object(fields).to_string());
+}
@@ -0,0 +1,242 @@
+use crate::{diagnostics, Level, LoggerError, LoggerResult};
+use std::{
+    env,
+    ffi::c_void,
+    fmt::{self, Display},
+    panic::Location,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, OnceLock, PoisonError, RwLock},
+};
+
+/// The environment variable that is read for the initial [`Filter`], if
+/// [`set_filter`] has not been called.
+pub const FILTER_ENV_VAR: &str = "CODECTRL_FILTER";
+
+/// Filter directives that switch logs on or off by where they were logged
+/// from, in the same syntax as `RUST_LOG`, i.e.
+/// `warn,my_crate::net=debug,my_crate::db=off`.
+///
+/// Each directive is a comma-separated `target=level`, where the target is
+/// either a module path, which matches the callsite's module and any module
+/// inside of it, or a file path (containing a `/` or ending in `.rs`), which
+/// matches any callsite in a file whose path contains it. The level is any
+/// [`Level`] or `off`. A directive with only a level applies to callsites that
+/// no other directive matches, and a directive with only a target enables every
+/// level for it.
+///
+/// The most specific (longest) target that matches the callsite decides
+/// whether its logs are sent. Callsites that no directive matches are not
+/// filtered. The filter only turns logs off, so a log must also pass the
+/// minimum level set by [`set_min_level`](crate::set_min_level).
+///
+/// The filter is read from [`FILTER_ENV_VAR`] once, the first time that a log
+/// is created, and can be replaced with [`set_filter`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    directives: Vec<Directive>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Directive {
+    target: Option<String>,
+    // `None` means that the target is turned off.
+    level: Option<Level>,
+}
+
+impl Directive {
+    fn matches(&self, module_path: &str, file: &str) -> bool {
+        let Some(target) = &self.target else {
+            return true;
+        };
+
+        if target.contains('/') || target.ends_with(".rs") {
+            return file.replace('\\', "/").contains(target.as_str());
+        }
+
+        module_path
+            .strip_prefix(target.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+    }
+}
+
+impl Filter {
+    /// Parses filter directives, see [`Filter`] for the syntax.
+    pub fn parse(directives: &str) -> LoggerResult<Self> { directives.parse() }
+
+    /// A filter that does not filter anything.
+    pub fn none() -> Self { Self::default() }
+
+    /// Whether a log with `level` from a callsite in `module_path` and `file`
+    /// is sent.
+    pub fn enabled(&self, level: Level, module_path: &str, file: &str) -> bool {
+        let directive = self
+            .directives
+            .iter()
+            .filter(|directive| directive.matches(module_path, file))
+            .max_by_key(|directive| directive.target.as_ref().map(String::len));
+
+        match directive {
+            Some(directive) => directive.level.is_some_and(|min| level >= min),
+            None => true,
+        }
+    }
+
+    // Whether any callsite could send a log with `level`, so that callsites
+    // don't need to be resolved for levels that are always filtered out.
+    pub(crate) fn may_enable(&self, level: Level) -> bool {
+        let has_default = self
+            .directives
+            .iter()
+            .any(|directive| directive.target.is_none());
+
+        !has_default
+            || self
+                .directives
+                .iter()
+                .any(|directive| directive.level.is_some_and(|min| level >= min))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool { self.directives.is_empty() }
+}
+
+impl FromStr for Filter {
+    type Err = LoggerError;
+
+    fn from_str(directives: &str) -> Result<Self, Self::Err> {
+        let directives = directives
+            .split(',')
+            .map(str::trim)
+            .filter(|directive| !directive.is_empty())
+            .map(parse_directive)
+            .collect::<LoggerResult<_>>()?;
+
+        Ok(Self { directives })
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, directive) in self.directives.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+
+            let level = directive
+                .level
+                .map_or_else(|| "off".to_string(), |level| level.as_str().to_lowercase());
+
+            match &directive.target {
+                Some(target) => write!(f, "{target}={level}")?,
+                None => f.write_str(&level)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_directive(directive: &str) -> LoggerResult<Directive> {
+    let parse_level = |level: &str| -> LoggerResult<Option<Level>> {
+        match level.trim() {
+            level if level.eq_ignore_ascii_case("off") => Ok(None),
+            level => level.parse().map(Some),
+        }
+    };
+
+    match directive.split_once('=') {
+        Some((target, level)) if !target.trim().is_empty() => Ok(Directive {
+            target: Some(target.trim().to_string()),
+            level: parse_level(level)?,
+        }),
+        Some(_) => Err(LoggerError::LoggerError(format!(
+            "Invalid filter directive, missing target: {directive}"
+        ))),
+        // A lone word is a level if it parses as one, otherwise it is a target
+        // with every level enabled.
+        None => match parse_level(directive) {
+            Ok(level) => Ok(Directive {
+                target: None,
+                level,
+            }),
+            Err(_) => Ok(Directive {
+                target: Some(directive.to_string()),
+                level: Some(Level::Trace),
+            }),
+        },
+    }
+}
+
+fn global() -> &'static RwLock<Arc<Filter>> {
+    static FILTER: OnceLock<RwLock<Arc<Filter>>> = OnceLock::new();
+
+    FILTER.get_or_init(|| {
+        let filter = match env::var(FILTER_ENV_VAR) {
+            Ok(directives) => directives.parse().unwrap_or_else(|e| {
+                diagnostics::warn(
+                    "Ignoring an invalid filter",
+                    format_args!("{FILTER_ENV_VAR}: {e}"),
+                );
+                Filter::none()
+            }),
+            Err(_) => Filter::none(),
+        };
+
+        RwLock::new(Arc::new(filter))
+    })
+}
+
+/// Sets the global [`Filter`], overriding [`FILTER_ENV_VAR`].
+pub fn set_filter(filter: Filter) {
+    *global().write().unwrap_or_else(PoisonError::into_inner) = Arc::new(filter);
+}
+
+/// Returns the global [`Filter`].
+pub fn filter() -> Arc<Filter> {
+    global()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+// The module path of the function that `caller` is in, for logs from functions
+// rather than the macros, which capture it with `module_path!`. Frames are
+// resolved one at a time from the innermost, so only the frames up to the
+// caller are resolved, rather than the whole stack trace. Empty if there is no
+// debug info to resolve the frame with.
+pub(crate) fn caller_module_path(caller: &Location) -> String {
+    let mut ips: Vec<*mut c_void> = Vec::new();
+
+    backtrace::trace(|frame| {
+        ips.push(frame.ip());
+        true
+    });
+
+    let mut module_path = None;
+
+    for ip in ips {
+        backtrace::resolve(ip, |symbol| {
+            let is_caller = symbol.lineno() == Some(caller.line())
+                && symbol
+                    .filename()
+                    .is_some_and(|file| file.ends_with(Path::new(caller.file())));
+
+            if let (true, None, Some(name)) = (is_caller, &module_path, symbol.name()) {
+                // The alternate form leaves out the symbol's hash, so that
+                // dropping the last segment leaves the function's module.
+                let name = format!("{name:#}");
+
+                module_path = Some(match name.rsplit_once("::") {
+                    Some((path, _)) => path.to_string(),
+                    None => name,
+                });
+            }
+        });
+
+        if module_path.is_some() {
+            break;
+        }
+    }
+
+    module_path.unwrap_or_default()
+}
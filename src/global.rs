@@ -0,0 +1,459 @@
+use crate::{
+    admit, create_log, diagnostics, limits, runtime::Handle, transport::Endpoints,
+    ConnectionCheck, Field, Level, Logger, LoggerError, LoggerResult, Message,
+    DEFAULT_CONNECT_TIMEOUT, DEFAULT_FAILOVER_COOLDOWN,
+};
+use serde::Serialize;
+use std::{
+    env,
+    fmt::Debug,
+    panic::Location,
+    sync::{Arc, OnceLock, PoisonError, RwLock},
+    time::Duration,
+};
+
+/// The environment variable that is read by [`Config::from_env`] for the host
+/// of the gRPC server.
+pub const HOST_ENV_VAR: &str = "CODECTRL_HOST";
+
+/// The environment variable that is read by [`Config::from_env`] for the port
+/// of the gRPC server.
+pub const PORT_ENV_VAR: &str = "CODECTRL_PORT";
+
+/// The configuration of the global logger, installed with [`init`] and used by
+/// the free logging functions, i.e. [`info`](crate::info()), and macros, i.e.
+/// [`info!`](crate::info!).
+///
+/// ```no_run
+/// use codectrl::Config;
+///
+/// # fn main() -> Result<(), codectrl::LoggerError> {
+/// codectrl::init(Config {
+///     port: "3003".into(),
+///     ..Default::default()
+/// })?;
+///
+/// codectrl::info!("Connected to {} peers", 3)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The host IP address of the gRPC server, or the path of its Unix domain
+    /// socket, see [`UNIX_SOCKET_PREFIX`](crate::UNIX_SOCKET_PREFIX). Defaults
+    /// to `127.0.0.1`.
+    pub host: String,
+    /// The port of the gRPC server. Defaults to `3002`.
+    pub port: String,
+    /// The servers to fail over to, in order, as host and port pairs, if the
+    /// server at `host` and `port` can't be connected to. See
+    /// [`LogBatch::failover`](crate::LogBatch::failover). Defaults to none.
+    pub failover: Vec<(String, String)>,
+    /// How long a server that was failed over to is used before `host` is
+    /// tried again. Defaults to [`DEFAULT_FAILOVER_COOLDOWN`].
+    pub failover_cooldown: Duration,
+    /// The servers that every log is also sent to, as host and port pairs. See
+    /// [`set_fan_out`](crate::set_fan_out). Defaults to none.
+    pub fan_out: Vec<(String, String)>,
+    /// How long connecting to each server may take before the next one is
+    /// tried, or sending fails. Defaults to [`DEFAULT_CONNECT_TIMEOUT`].
+    pub connect_timeout: Duration,
+    /// The number of lines around the log to include in its code snippet.
+    /// Defaults to 3.
+    pub surround: u32,
+    /// A tokio runtime to send logs with, rather than the runtime that is
+    /// shared by the crate. Must not be used from inside of that runtime, as
+    /// sending blocks until it is done.
+    pub tokio_runtime: Option<Handle>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".into(),
+            port: "3002".into(),
+            failover: Vec::new(),
+            failover_cooldown: DEFAULT_FAILOVER_COOLDOWN,
+            fan_out: Vec::new(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            surround: 3,
+            tokio_runtime: None,
+        }
+    }
+}
+
+impl Config {
+    /// Creates a config from the [`HOST_ENV_VAR`] and [`PORT_ENV_VAR`]
+    /// environment variables, using the defaults for any that are not set.
+    pub fn from_env() -> LoggerResult<Self> {
+        let mut config = Self::default();
+
+        if let Ok(host) = env::var(HOST_ENV_VAR) {
+            config.host = host;
+        }
+
+        if let Ok(port) = env::var(PORT_ENV_VAR) {
+            if port.trim().parse::<u16>().is_err() {
+                return Err(LoggerError::LoggerError(format!(
+                    "Invalid port in {PORT_ENV_VAR}: {port}"
+                )));
+            }
+
+            config.port = port.trim().to_string();
+        }
+
+        Ok(config)
+    }
+
+    pub(crate) fn endpoints(&self) -> Endpoints {
+        Endpoints::new(&self.host, &self.port)
+            .failover(
+                self.failover
+                    .iter()
+                    .map(|(host, port)| (host.as_str(), port.as_str())),
+                self.failover_cooldown,
+            )
+            .connect_timeout(self.connect_timeout)
+            .fan_out(
+                self.fan_out
+                    .iter()
+                    .map(|(host, port)| (host.as_str(), port.as_str())),
+            )
+    }
+}
+
+fn global_config() -> &'static RwLock<Option<Arc<Config>>> {
+    static CONFIG: OnceLock<RwLock<Option<Arc<Config>>>> = OnceLock::new();
+
+    CONFIG.get_or_init(Default::default)
+}
+
+/// Installs the global logger. Returns an error if it was already installed,
+/// see [`replace_global`] to replace it.
+pub fn init(config: Config) -> LoggerResult<()> {
+    let mut global = global_config()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    if global.is_some() {
+        return Err(LoggerError::LoggerError(
+            "The global logger has already been initialised".to_string(),
+        ));
+    }
+
+    *global = Some(Arc::new(config));
+
+    Ok(())
+}
+
+/// Installs the global logger with [`Config::from_env`].
+pub fn init_from_env() -> LoggerResult<()> { init(Config::from_env()?) }
+
+/// Replaces the global logger, whether or not it was installed, returning the
+/// previous one. Mainly useful for pointing tests at a different server.
+pub fn replace_global(config: Config) -> Option<Arc<Config>> {
+    global_config()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .replace(Arc::new(config))
+}
+
+/// Returns the config of the global logger, or `None` if it was not installed.
+pub fn global() -> Option<Arc<Config>> {
+    global_config()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+// The global config, or the default config if the global logger was not
+// installed, the same as passing `None` to each argument of the `Logger`
+// functions. The config is fetched up front rather than logging inside of a
+// closure, which would hide the callsite from `#[track_caller]`.
+pub(crate) fn config() -> Arc<Config> { global().unwrap_or_default() }
+
+// Creates and sends a log with the global logger.
+#[track_caller]
+pub(crate) fn send(level: Level, message: Message) -> LoggerResult<()> {
+    let config = config();
+
+    let Some(log) =
+        create_log(level, message, &limits(), Some(config.surround), None, None)
+    else {
+        return Ok(());
+    };
+
+    Logger::send_log_to(log, config.endpoints(), config.tokio_runtime.as_ref())
+}
+
+/// [`Logger::check_connection`] using the global logger. Only its `host` and
+/// `port` are checked, not the servers that it fails over to.
+pub fn check_connection() -> ConnectionCheck {
+    let config = config();
+
+    Logger::check_connection(
+        Some(&config.host),
+        Some(&config.port),
+        config.tokio_runtime.as_ref(),
+    )
+}
+
+/// [`Logger::log`] using the global logger.
+#[track_caller]
+pub fn log<T: Debug>(message: T) -> LoggerResult<()> { log_at(Level::Info, message) }
+
+/// [`Logger::log_at`] using the global logger.
+#[track_caller]
+pub fn log_at<T: Debug>(level: Level, message: T) -> LoggerResult<()> {
+    if !admit(level.is_enabled()) {
+        return Ok(());
+    }
+
+    send(level, Message::debug(&message))
+}
+
+/// [`Logger::log_with`] using the global logger.
+#[track_caller]
+pub fn log_with<T: Debug>(
+    fields: impl IntoIterator<Item = Field>,
+    message: T,
+) -> LoggerResult<()> {
+    if !admit(Level::Info.is_enabled()) {
+        return Ok(());
+    }
+
+    send(Level::Info, Message::debug(&message).with_fields(fields))
+}
+
+/// [`Logger::log_serialized`] using the global logger.
+#[track_caller]
+pub fn log_serialized<T: Serialize>(message: T) -> LoggerResult<()> {
+    if !admit(Level::Info.is_enabled()) {
+        return Ok(());
+    }
+
+    send(Level::Info, Message::serialized(&message))
+}
+
+/// [`log_at`] with [`Level::Trace`].
+#[track_caller]
+pub fn trace<T: Debug>(message: T) -> LoggerResult<()> { log_at(Level::Trace, message) }
+
+/// [`log_at`] with [`Level::Debug`].
+#[track_caller]
+pub fn debug<T: Debug>(message: T) -> LoggerResult<()> { log_at(Level::Debug, message) }
+
+/// [`log_at`] with [`Level::Info`].
+#[track_caller]
+pub fn info<T: Debug>(message: T) -> LoggerResult<()> { log_at(Level::Info, message) }
+
+/// [`log_at`] with [`Level::Warn`].
+#[track_caller]
+pub fn warn<T: Debug>(message: T) -> LoggerResult<()> { log_at(Level::Warn, message) }
+
+/// [`log_at`] with [`Level::Error`].
+#[track_caller]
+pub fn error<T: Debug>(message: T) -> LoggerResult<()> { log_at(Level::Error, message) }
+
+// Used by the logging macros to check the level before formatting the message.
+#[doc(hidden)]
+pub fn __is_enabled(level: Level) -> bool { admit(level.is_enabled()) }
+
+// Used by the logging macros, which embed the module path of their callsite for
+// the filter, and the source around it for binaries that are built without
+// debug info.
+#[doc(hidden)]
+#[track_caller]
+pub fn __log_at<T: Debug>(
+    level: Level,
+    message: T,
+    module_path: &'static str,
+    source: &'static [(u32, &'static str)],
+) -> LoggerResult<()> {
+    if !admit(level.is_enabled()) {
+        return Ok(());
+    }
+
+    send(
+        level,
+        Message::debug(&message)
+            .in_module(module_path)
+            .with_source(source),
+    )
+}
+
+// Used by `dbg!`, which has no way of returning an error.
+#[doc(hidden)]
+#[track_caller]
+pub fn __dbg<T: Debug>(
+    expression: Option<&'static str>,
+    value: &T,
+    module_path: &'static str,
+    source: &'static [(u32, &'static str)],
+) {
+    if !admit(Level::Debug.is_enabled()) {
+        return;
+    }
+
+    let location = Location::caller();
+    let message = match expression {
+        Some(expression) => Message {
+            text: format!("{expression} = {value:#?}"),
+            ..Message::debug(value)
+        },
+        None => Message::debug(&format!(
+            "[{}:{}:{}]",
+            location.file(),
+            location.line(),
+            location.column()
+        )),
+    };
+
+    let message = message.in_module(module_path).with_source(source);
+
+    if let Err(e) = send(Level::Debug, message) {
+        diagnostics::warn("Could not send dbg! log", e);
+    }
+}
+
+/// Logs with the global logger at the given [`Level`]. Takes either a single
+/// [`Debug`] value, or a format string and its arguments, which are sent as a
+/// [`String`].
+///
+/// ```no_run
+/// use codectrl::Level;
+///
+/// # fn main() -> Result<(), codectrl::LoggerError> {
+/// let peers = vec!["10.0.0.2", "10.0.0.3"];
+/// let count = peers.len();
+///
+/// codectrl::log!(Level::Debug, peers)?;
+/// codectrl::log!(Level::Warn, "{} peers did not respond", 2)?;
+/// // Variables can be captured inline, as with `format!`.
+/// codectrl::log!(Level::Info, "{count} peers configured")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Debug`]: std::fmt::Debug
+#[macro_export]
+#[collapse_debuginfo(yes)]
+macro_rules! log {
+    ($level:expr, $format:literal, $($arg:tt)+) => {
+        match $level {
+            // Only format the message if it will be sent.
+            level if $crate::__is_enabled(level) => {
+                $crate::__log_at(
+                    level,
+                    ::std::format!($format, $($arg)+),
+                    ::std::module_path!(),
+                    $crate::__source_lines!($format),
+                )
+            },
+            _ => ::std::result::Result::Ok(()),
+        }
+    };
+    ($level:expr, $format:literal $(,)?) => {
+        match $level {
+            level if $crate::__is_enabled(level) => {
+                $crate::__log_at(
+                    level,
+                    $crate::__format_literal!($format),
+                    ::std::module_path!(),
+                    $crate::__source_lines!($format),
+                )
+            },
+            _ => ::std::result::Result::Ok(()),
+        }
+    };
+    ($level:expr, $message:expr $(,)?) => {
+        $crate::__log_at(
+            $level,
+            $message,
+            ::std::module_path!(),
+            $crate::__source_lines!($message),
+        )
+    };
+}
+
+/// [`log!`](crate::log!) with [`Level::Trace`](crate::Level::Trace).
+#[macro_export]
+#[collapse_debuginfo(yes)]
+macro_rules! trace {
+    ($($arg:tt)+) => { $crate::log!($crate::Level::Trace, $($arg)+) };
+}
+
+/// [`log!`](crate::log!) with [`Level::Debug`](crate::Level::Debug).
+#[macro_export]
+#[collapse_debuginfo(yes)]
+macro_rules! debug {
+    ($($arg:tt)+) => { $crate::log!($crate::Level::Debug, $($arg)+) };
+}
+
+/// [`log!`](crate::log!) with [`Level::Info`](crate::Level::Info).
+#[macro_export]
+#[collapse_debuginfo(yes)]
+macro_rules! info {
+    ($($arg:tt)+) => { $crate::log!($crate::Level::Info, $($arg)+) };
+}
+
+/// [`log!`](crate::log!) with [`Level::Warn`](crate::Level::Warn).
+#[macro_export]
+#[collapse_debuginfo(yes)]
+macro_rules! warn {
+    ($($arg:tt)+) => { $crate::log!($crate::Level::Warn, $($arg)+) };
+}
+
+/// [`log!`](crate::log!) with [`Level::Error`](crate::Level::Error).
+#[macro_export]
+#[collapse_debuginfo(yes)]
+macro_rules! error {
+    ($($arg:tt)+) => { $crate::log!($crate::Level::Error, $($arg)+) };
+}
+
+/// Works like [`std::dbg!`], but sends the expression and its [`Debug`] value
+/// to the global logger with [`Level::Debug`](crate::Level::Debug), pointing
+/// at the line of the macro. Returns the value of the expression, or a tuple of
+/// the values if given several expressions.
+///
+/// Unlike the other logging macros, this never returns an error: if the log
+/// could not be sent, the error is sent to the [`Diagnostics`] instead.
+///
+/// [`Diagnostics`]: crate::Diagnostics
+///
+/// ```no_run
+/// let a = 2;
+/// let b = codectrl::dbg!(a * 2) + 1;
+///
+/// let (c, d) = codectrl::dbg!(a, b);
+/// ```
+///
+/// [`Debug`]: std::fmt::Debug
+#[macro_export]
+#[collapse_debuginfo(yes)]
+macro_rules! dbg {
+    () => {
+        $crate::__dbg(
+            ::std::option::Option::None,
+            &(),
+            ::std::module_path!(),
+            $crate::__source_lines!(()),
+        )
+    };
+    ($value:expr $(,)?) => {
+        match $value {
+            value => {
+                $crate::__dbg(
+                    ::std::option::Option::Some(::std::stringify!($value)),
+                    &value,
+                    ::std::module_path!(),
+                    $crate::__source_lines!($value),
+                );
+                value
+            },
+        }
+    };
+    ($($value:expr),+ $(,)?) => {
+        ($($crate::dbg!($value)),+,)
+    };
+}
@@ -0,0 +1,131 @@
+use crate::LoggerError;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    fmt::{self, Display},
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// The environment variable that is read to find the initial minimum
+/// [`Level`], if [`set_min_level`] has not been called.
+pub const LEVEL_ENV_VAR: &str = "CODECTRL_LEVEL";
+
+// Sentinel value meaning that the minimum level has not been set or read from
+// the environment yet.
+const UNSET: u8 = u8::MAX;
+
+static MIN_LEVEL: AtomicU8 = AtomicU8::new(UNSET);
+
+/// The severity of a log.
+///
+/// The level is sent to the server as a prefix of the `message_type` field of
+/// the [`Log`], i.e. `[WARN] &str`, so that any CodeCTRL front-end can display
+/// it without needing to know about it.
+///
+/// [`Log`]: codectrl_protobuf_bindings::data::Log
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum Level {
+    Trace,
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// The upper-case name of the level, as it is sent in the `message_type`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Trace => "TRACE",
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        }
+    }
+
+    /// Whether a log of this level passes the global minimum level. See
+    /// [`min_level`]. Always `false` with the `disabled` feature.
+    pub fn is_enabled(&self) -> bool {
+        !cfg!(feature = "disabled") && *self >= min_level()
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Trace),
+            1 => Some(Self::Debug),
+            2 => Some(Self::Info),
+            3 => Some(Self::Warn),
+            4 => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Level {
+    type Err = LoggerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "trace" => Ok(Self::Trace),
+            "debug" => Ok(Self::Debug),
+            "info" => Ok(Self::Info),
+            "warn" | "warning" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            _ => Err(LoggerError::LoggerError(format!("Unknown log level: {s}"))),
+        }
+    }
+}
+
+/// Sets the global minimum [`Level`]. Any log below this level is dropped
+/// before a backtrace is captured or a connection is made.
+///
+/// This takes precedence over the `CODECTRL_LEVEL` environment variable.
+pub fn set_min_level(level: Level) { MIN_LEVEL.store(level as u8, Ordering::Relaxed); }
+
+/// Returns the global minimum [`Level`].
+///
+/// If [`set_min_level`] has not been called, this is read once from the
+/// `CODECTRL_LEVEL` environment variable, defaulting to [`Level::Trace`] (i.e.
+/// everything is sent) if it is missing or invalid.
+pub fn min_level() -> Level {
+    if let Some(level) = Level::from_u8(MIN_LEVEL.load(Ordering::Relaxed)) {
+        return level;
+    }
+
+    let level = env::var(LEVEL_ENV_VAR)
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(Level::Trace);
+
+    // Only store the environment's value if nothing has been set in the
+    // meantime, so that we never overwrite an explicit `set_min_level`.
+    let _ = MIN_LEVEL.compare_exchange(
+        UNSET,
+        level as u8,
+        Ordering::Relaxed,
+        Ordering::Relaxed,
+    );
+
+    Level::from_u8(MIN_LEVEL.load(Ordering::Relaxed)).unwrap_or(level)
+}
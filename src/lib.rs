@@ -1,29 +1,101 @@
 #![doc = include_str!("../README.md")]
-
-// TODO(important): Replace tokio runtime handlers with tasks and LocalSet.
-
-#[cfg(test)]
+// `LoggerError` carries a `tonic::Status`, which makes it large, but it is the
+// error of every public function and changing it would break their signatures.
+#![allow(clippy::result_large_err)]
+
+mod auto_flush;
+pub mod condition;
+#[cfg(not(feature = "logging"))]
+mod data;
+mod diagnostics;
+mod fan_out;
+mod fields;
+mod filter;
+mod global;
+mod level;
+mod limits;
+mod rate_limit;
+mod redact;
+mod report;
+mod runtime;
+mod scope;
+mod stats;
+#[cfg(all(test, feature = "rt-tokio", not(feature = "disabled")))]
 mod tests;
+mod timer;
+mod transport;
+#[cfg(not(feature = "disabled"))]
+mod worker;
+
+pub use auto_flush::AutoFlushBatch;
+pub use condition::Condition;
+pub use diagnostics::{set_diagnostics, Diagnostics};
+pub use fields::{Field, FIELDS_PREFIX};
+pub use filter::{filter, set_filter, Filter, FILTER_ENV_VAR};
+#[doc(hidden)]
+pub use global::{__dbg, __is_enabled, __log_at};
+pub use global::{
+    check_connection, debug, error, global, info, init, init_from_env, log, log_at,
+    log_serialized, log_with, replace_global, trace, warn, Config, HOST_ENV_VAR,
+    PORT_ENV_VAR,
+};
+pub use level::{min_level, set_min_level, Level, LEVEL_ENV_VAR};
+pub use limits::{limits, set_limits, Limits};
+pub use rate_limit::RateLimit;
+pub use redact::{set_redactor, Redactor, REDACTED};
+pub use report::{BatchReport, DeliveryMode, EndpointReport, FailedLog};
+pub use runtime::Handle;
+pub use scope::{enter, in_scope, scope, scopes, InScope, ScopeGuard, SCOPES_FIELD};
+pub use stats::{LatencyHistogram, Stats};
+pub use timer::TimerGuard;
+pub use transport::{
+    set_failover, set_fan_out, ConnectionCheck, CONNECTION_CHECK_TIMEOUT,
+    DEFAULT_CONNECT_TIMEOUT, DEFAULT_FAILOVER_COOLDOWN, SEND_TIMEOUT, UNIX_SOCKET_PREFIX,
+};
+
+#[doc(hidden)]
+pub use codectrl_macros::format_literal as __format_literal;
+#[doc(hidden)]
+pub use codectrl_macros::source_lines as __source_lines;
 
 use backtrace::Backtrace;
-use codectrl_protobuf_bindings::{
-    data::{BacktraceData, Log},
-    logs_service::{LoggerClient, RequestResult, RequestStatus},
-};
-use futures_util::stream;
+#[cfg(feature = "logging")]
+use codectrl_protobuf_bindings::data::{BacktraceData, Log};
+#[cfg(feature = "logging")]
+use codectrl_protobuf_bindings::logs_service::RequestResult;
+#[cfg(not(feature = "disabled"))]
+use codectrl_protobuf_bindings::logs_service::RequestStatus;
+#[cfg(not(feature = "logging"))]
+use data::{BacktraceData, Log};
+use fan_out::FanOut;
+#[cfg(not(feature = "disabled"))]
+use futures_util::{stream, FutureExt};
 use hashbag::HashBag;
 use serde::{Deserialize, Serialize};
+use stats::Counter;
 use std::{
     cell::RefCell,
-    collections::{BTreeMap, VecDeque},
+    collections::{vec_deque, BTreeMap, VecDeque},
     env,
-    fmt::Debug,
+    fmt::{self, Debug, Display},
     fs,
     fs::File,
+    future::Future,
     io::{self, prelude::*, BufReader},
+    mem,
+    panic::Location,
+    time::Duration,
+};
+#[cfg(not(feature = "disabled"))]
+use std::{
+    sync::{mpsc as std_mpsc, Arc},
+    time::Instant,
 };
-use tokio::runtime::{Handle, Runtime};
+#[cfg(not(feature = "disabled"))]
+use tokio::sync::oneshot;
+#[cfg(not(feature = "disabled"))]
 use tonic::Request;
+use transport::Endpoints;
 
 /// The Error type used by [`Logger`] and [`LogBatch`] whenever something can
 /// potentially fail.
@@ -31,9 +103,11 @@ use tonic::Request;
 pub enum LoggerError {
     /// An error that has been generated by Tonic during transportation to/from
     /// the server.
+    #[cfg(feature = "logging")]
     #[error("Tonic reported an error during transport: {0}")]
     TonicTransportError(#[from] tonic::transport::Error),
     /// An status code generated by Tonic as a result of a request.
+    #[cfg(feature = "logging")]
     #[error("Tonic request resulted in status code: {0}")]
     TonicStatusCode(#[from] tonic::Status),
     /// Any error that is invoked with [`std::error::Error`], typically a Tokio
@@ -54,6 +128,7 @@ pub enum LoggerError {
     Other(#[from] anyhow::Error),
 }
 
+#[cfg(feature = "logging")]
 impl From<RequestResult> for LoggerError {
     fn from(res: RequestResult) -> Self {
         Self::LogServerError {
@@ -69,28 +144,278 @@ type LoggerResult<T> = Result<T, LoggerError>;
 enum Warning {
     CompiledWithoutDebugInfo,
     NoColumnNumberWindows,
+    Suppressed(u64),
+    SerialisationFailed(String),
+    Redacted,
+    Truncated(usize),
+    TruncatedForBatch(usize),
+    DepthLimited(usize),
+    BatchLimitReached(u64),
 }
 
-impl ToString for Warning {
-    fn to_string(&self) -> String {
+impl Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::CompiledWithoutDebugInfo =>
-                "File was compiled without debug info, meaning information was lost",
-            Self::NoColumnNumberWindows =>
+            Self::CompiledWithoutDebugInfo => f.write_str(
+                "File was compiled without debug info, so there is no stack trace and \
+                 the log points at its callsite",
+            ),
+            Self::NoColumnNumberWindows => f.write_str(
                 "File was compiled on Windows, which does not always report column \
                  numbers",
+            ),
+            Self::Suppressed(count) => write!(
+                f,
+                "{count} log(s) from this callsite were suppressed by its rate limit \
+                 since the last one was sent"
+            ),
+            Self::SerialisationFailed(error) => {
+                write!(f, "Message could not be serialised as JSON: {error}")
+            },
+            Self::Redacted => f.write_str(
+                "Sensitive values were redacted from the message, code snippet or stack \
+                 trace",
+            ),
+            Self::Truncated(original) => write!(
+                f,
+                "Message was truncated from its original size of {original} bytes"
+            ),
+            Self::TruncatedForBatch(size) => write!(
+                f,
+                "Message was truncated from {size} bytes to fit into the size limit of \
+                 the batch"
+            ),
+            Self::DepthLimited(max_depth) => write!(
+                f,
+                "Message was nested deeper than {max_depth} levels and was cut off"
+            ),
+            Self::BatchLimitReached(dropped) => write!(
+                f,
+                "The size limit of the batch was reached, {dropped} log(s) after this \
+                 one were dropped"
+            ),
+        }
+    }
+}
+
+/// The prefix given to the type name in the `message_type` of a [`Log`] whose
+/// `message` is JSON, i.e. `[INFO] json:alloc::vec::Vec<u32>`. See
+/// [`Logger::log_serialized`].
+///
+/// [`Log`]: codectrl_protobuf_bindings::data::Log
+pub const JSON_MESSAGE_TYPE_PREFIX: &str = "json:";
+
+// The rendered form of a message passed into any of the logging functions.
+struct Message {
+    text: String,
+    message_type: String,
+    warnings: Vec<String>,
+    fields: Vec<Field>,
+    // Whether `text` is pretty-printed `Debug` output, which can have its
+    // depth limited.
+    is_debug: bool,
+    // Overrides the line that the log points at, which is otherwise the
+    // innermost frame of the stack trace.
+    location: Option<&'static Location<'static>>,
+    // The lines around the callsite, embedded at compile time for when the
+    // source can't be read from the stack trace.
+    source: Option<&'static [(u32, &'static str)]>,
+    // The module of the callsite, captured by the logging macros, for the
+    // filter to match without resolving the caller's frame.
+    module_path: Option<&'static str>,
+}
+
+impl Message {
+    fn debug<T: Debug>(message: &T) -> Self {
+        Self {
+            text: format!("{:#?}", message),
+            message_type: std::any::type_name::<T>().to_string(),
+            warnings: Vec::new(),
+            fields: Vec::new(),
+            is_debug: true,
+            location: None,
+            source: None,
+            module_path: None,
+        }
+    }
+
+    // Falls back to a placeholder message and a warning with the serialisation
+    // error if `message` can't be represented as JSON, rather than losing the
+    // log.
+    fn serialized<T: Serialize>(message: &T) -> Self {
+        let type_name = std::any::type_name::<T>();
+
+        match serde_json::to_string_pretty(message) {
+            Ok(text) => Self {
+                text,
+                message_type: format!("{JSON_MESSAGE_TYPE_PREFIX}{type_name}"),
+                warnings: Vec::new(),
+                fields: Vec::new(),
+                is_debug: false,
+                location: None,
+                source: None,
+                module_path: None,
+            },
+            Err(e) => Self {
+                text: format!("<{type_name} could not be serialised>"),
+                message_type: type_name.to_string(),
+                warnings: vec![Warning::SerialisationFailed(e.to_string()).to_string()],
+                fields: Vec::new(),
+                is_debug: false,
+                location: None,
+                source: None,
+                module_path: None,
+            },
+        }
+    }
+
+    fn with_fields(mut self, fields: impl IntoIterator<Item = Field>) -> Self {
+        self.fields.extend(fields);
+        self
+    }
+
+    fn at(mut self, location: &'static Location<'static>) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    fn with_source(mut self, source: &'static [(u32, &'static str)]) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    fn in_module(mut self, module_path: &'static str) -> Self {
+        self.module_path = Some(module_path);
+        self
+    }
+
+    fn limit_depth(&mut self, limits: &Limits) {
+        if let (Some(max_depth), true) = (limits.max_depth, self.is_debug) {
+            if limits::limit_depth(&mut self.text, max_depth) {
+                self.warnings
+                    .push(Warning::DepthLimited(max_depth).to_string());
+            }
+        }
+    }
+
+    // Appends the fields to the text and truncates it to the maximum size,
+    // redacting it first, as truncation could cut a secret in half so that it
+    // is no longer recognised. Returns whether anything was redacted.
+    //
+    // A JSON message has to stay valid JSON, so it carries no fields, and once
+    // it is truncated it is no longer marked as JSON.
+    fn finish(&mut self, redactor: &Redactor, limits: &Limits) -> bool {
+        let mut fields = String::new();
+        let json = self.message_type.starts_with(JSON_MESSAGE_TYPE_PREFIX);
+
+        if !json {
+            fields::append_fields(&mut fields, mem::take(&mut self.fields));
+        }
+
+        let redacted = redact::redact_in_place(redactor, &mut self.text)
+            | redact::redact_in_place(redactor, &mut fields);
+
+        let original = self.text.len() + fields.len();
+
+        if let Some(max_message_bytes) =
+            limits.max_message_bytes.filter(|max| original > *max)
+        {
+            // The fields are kept whole so that they can still be parsed, unless
+            // they don't fit by themselves.
+            match max_message_bytes.checked_sub(fields.len()) {
+                Some(max_text_bytes) => {
+                    limits::truncate(&mut self.text, max_text_bytes);
+                },
+                None => {
+                    self.text.push_str(&mem::take(&mut fields));
+                    limits::truncate(&mut self.text, max_message_bytes);
+                },
+            }
+
+            if json {
+                self.message_type.drain(..JSON_MESSAGE_TYPE_PREFIX.len());
+            }
+
+            self.warnings.push(Warning::Truncated(original).to_string());
         }
-        .into()
+
+        self.text.push_str(&fields);
+
+        redacted
+    }
+}
+
+// Whether a log passes the minimum level, its condition and `CODECTRL_DEBUG`,
+// which are checked before it is created. Every one of those checks goes
+// through here, so that the logs that don't pass are counted as filtered.
+//
+// With the `disabled` feature nothing passes, and nothing is counted. The level
+// checks, `Level::is_enabled` and `LogBatch::level_enabled`, fail as well, so
+// that conditions aren't evaluated either.
+fn admit(passes: bool) -> bool {
+    if cfg!(feature = "disabled") {
+        return false;
+    }
+
+    if !passes {
+        stats::add(Counter::Filtered, 1);
     }
+
+    passes
 }
 
-fn create_log<T: Debug>(
-    message: T,
+// The callsite of the log is used when there is no stack trace, i.e. when this
+// crate was compiled without debug info, so every function between the caller
+// and here must be `#[track_caller]`.
+#[track_caller]
+fn create_log(
+    level: Level,
+    mut message: Message,
+    limits: &Limits,
     surround: Option<u32>,
     function_name: Option<&str>,
     function_name_occurences: Option<&HashBag<&'static str>>,
-) -> Log {
+) -> Option<Log> {
     let function_name = function_name.unwrap_or_default();
+    let filter = filter::filter();
+
+    if !filter.may_enable(level) {
+        stats::add(Counter::Filtered, 1);
+        return None;
+    }
+
+    let location = message.location;
+    let source = message.source;
+    // `Location::caller` must be called directly, through a function pointer it
+    // points at the closure shim in `core`.
+    let caller = match location {
+        Some(location) => location,
+        None => Location::caller(),
+    };
+
+    // The filter is decided before the stack trace is captured, so that a
+    // filtered log costs at most the resolution of the caller's frame.
+    if !filter.is_empty() {
+        let module_path = match message.module_path {
+            Some(module_path) => module_path.to_string(),
+            None => filter::caller_module_path(caller),
+        };
+
+        if !filter.enabled(level, &module_path, caller.file()) {
+            stats::add(Counter::Filtered, 1);
+            return None;
+        }
+    }
+
+    message.limit_depth(limits);
+
+    // User fields come after the scopes, so they take precedence on a clash.
+    if let Some(scopes) = scope::field() {
+        message.fields.insert(0, scopes);
+    }
+
+    let redactor = redact::redactor();
+    let redacted = message.finish(&redactor, limits);
 
     let mut log = Log {
         uuid: "".to_string(),
@@ -98,26 +423,13 @@ fn create_log<T: Debug>(
         line_number: 0,
         file_name: String::new(),
         code_snippet: BTreeMap::new(),
-        message: format!("{:#?}", &message),
-        message_type: std::any::type_name::<T>().to_string(),
+        message: message.text,
+        message_type: format!("[{level}] {}", message.message_type),
         address: String::new(),
-        warnings: Vec::new(),
+        warnings: message.warnings,
         language: "Rust".into(),
     };
 
-    #[cfg(not(debug_assertions))]
-    eprintln!(
-        "Unfortunately, using this function without debug_assertions enabled will \
-         produce limited information. The stack trace, file path and line number will \
-         be missing from the final message that is sent to the server. Please consider \
-         guarding this function using #[cfg(debug_assertions)] so that this message \
-         does not re-appear."
-    );
-
-    #[cfg(not(debug_assertions))]
-    log.warnings
-        .push(Warning::CompiledWithoutDebugInfo.to_string());
-
     #[cfg(target_os = "windows")]
     log.warnings
         .push(Warning::NoColumnNumberWindows.to_string());
@@ -126,7 +438,27 @@ fn create_log<T: Debug>(
 
     Logger::get_stack_trace(&mut log);
 
-    if let Some(last) = log.stack.last() {
+    // `Location::file` is relative to where the crate was compiled, so the full
+    // path is taken from a frame in the same file.
+    let callsite = location.and_then(|location| {
+        log.stack
+            .iter()
+            .rev()
+            .find(|frame| frame.file_path.ends_with(location.file()))
+            .map(|frame| (frame.file_path.clone(), location.line()))
+    });
+
+    if let Some((file_path, line_number)) = callsite {
+        log.line_number = line_number;
+        log.code_snippet = Logger::get_code_snippet(
+            &file_path,
+            &mut log.line_number,
+            surround,
+            "",
+            None,
+        );
+        log.file_name = file_path;
+    } else if let Some(last) = log.stack.last() {
         log.line_number = last.line_number;
 
         log.code_snippet = Logger::get_code_snippet(
@@ -138,9 +470,101 @@ fn create_log<T: Debug>(
         );
 
         log.file_name = last.file_path.clone();
+    } else {
+        // Without debug info there is no stack trace, so the log points at the
+        // callsite instead, with its source either embedded by a macro or read
+        // from the source file if it is available.
+        log.line_number = caller.line();
+        log.file_name = caller.file().to_string();
+
+        if source.is_none() {
+            log.code_snippet = Logger::get_code_snippet(
+                caller.file(),
+                &mut log.line_number,
+                surround,
+                "",
+                None,
+            );
+        }
+
+        log.warnings
+            .push(Warning::CompiledWithoutDebugInfo.to_string());
+    }
+
+    // The source file can't be read when a binary runs away from where it was
+    // compiled, even with line tables, so the source that a macro embedded at
+    // the callsite is used instead.
+    if let (true, Some(source)) = (
+        log.code_snippet.is_empty() && log.file_name.ends_with(caller.file()),
+        source,
+    ) {
+        log.code_snippet = embedded_snippet(source, log.line_number, surround);
+    }
+
+    if redactor.redact_source(&mut log) || redacted {
+        log.warnings.push(Warning::Redacted.to_string());
+    }
+
+    stats::add(Counter::Created, 1);
+
+    Some(log)
+}
+
+#[cfg(not(feature = "disabled"))]
+fn worker_gone() -> LoggerError {
+    LoggerError::LoggerError("The task that was sending the log did not finish".into())
+}
+
+// The error of anything that needs the network when logging is compiled out.
+#[cfg(feature = "disabled")]
+fn disabled() -> LoggerError {
+    LoggerError::LoggerError("Logging is disabled by the `disabled` feature".into())
+}
+
+// The lines of `source`, as embedded by `__source_lines!`, within `surround`
+// lines of `line_number`.
+fn embedded_snippet(
+    source: &[(u32, &str)],
+    line_number: u32,
+    surround: u32,
+) -> BTreeMap<u32, String> {
+    source
+        .iter()
+        .filter(|(n, _)| n.abs_diff(line_number) <= surround)
+        .map(|(n, line)| (*n, line.to_string()))
+        .collect()
+}
+
+// Removes `JSON_MESSAGE_TYPE_PREFIX` from the `message_type` of a log, i.e.
+// `[INFO] json:my_crate::Request`, once its message is no longer valid JSON.
+fn unmark_json(message_type: &mut String) {
+    if let Some(index) = message_type.find("] ") {
+        let start = index + 2;
+
+        if message_type[start..].starts_with(JSON_MESSAGE_TYPE_PREFIX) {
+            message_type.drain(start..start + JSON_MESSAGE_TYPE_PREFIX.len());
+        }
     }
+}
 
-    log
+// Turns the result that the server replied with into an error, if it is one.
+#[cfg(not(feature = "disabled"))]
+fn check_result(result: RequestResult) -> LoggerResult<()> {
+    match result {
+        RequestResult { status, .. } if status == i32::from(RequestStatus::Confirmed) =>
+            Ok(()),
+        RequestResult {
+            message,
+            status,
+            auth_status,
+        } if status == i32::from(RequestStatus::Error) => Err(RequestResult {
+            message,
+            status,
+            auth_status,
+        }
+        .into()),
+        RequestResult { .. } => unreachable!(),
+    }
 }
 
 /// Type used for generating batch logs to be sent by [`Logger`].
@@ -151,6 +575,18 @@ pub struct LogBatch<'a> {
     host: &'static str,
     port: &'static str,
     surround: u32,
+    min_level: Option<Level>,
+    limits: Limits,
+    delivery_mode: DeliveryMode,
+    fan_out: Vec<FanOut>,
+    failover: Vec<(&'static str, &'static str)>,
+    failover_cooldown: Duration,
+    connect_timeout: Duration,
+    // The number of logs at the front of the batch that failed to be sent to
+    // `host` and are already in the backlogs of the `fan_out` endpoints.
+    retained: usize,
+    batch_bytes: usize,
+    dropped: u64,
     function_name_occurences: HashBag<&'static str>,
 }
 
@@ -163,11 +599,22 @@ impl<'a> LogBatch<'a> {
             host: "127.0.0.1",
             port: "3002",
             surround: 3,
+            min_level: None,
+            limits: limits(),
+            delivery_mode: DeliveryMode::default(),
+            fan_out: Vec::new(),
+            failover: Vec::new(),
+            failover_cooldown: DEFAULT_FAILOVER_COOLDOWN,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            retained: 0,
+            batch_bytes: 0,
+            dropped: 0,
             function_name_occurences: HashBag::new(),
         }
     }
 
-    /// Sets the host IP address of the gRPC server to connect to.
+    /// Sets the host IP address of the gRPC server to connect to, or the path
+    /// of its Unix domain socket, see [`UNIX_SOCKET_PREFIX`].
     pub fn host(mut self, host: &'static str) -> Self {
         self.host = host;
         self
@@ -180,8 +627,8 @@ impl<'a> LogBatch<'a> {
     }
 
     /// If a tokio runtime is already present in the parent scope, you can pass
-    /// it here so that a new tokio runtime is not created when the batch is
-    /// sent.
+    /// it here so that the batch is sent on it, rather than on the runtime that
+    /// is shared by the crate.
     pub fn tokio_runtime(mut self, rt: &'a Handle) -> Self {
         self.tokio_runtime = Some(rt);
         self
@@ -196,281 +643,1564 @@ impl<'a> LogBatch<'a> {
         self
     }
 
-    /// Batch equivelent of [`Logger::log`]. See [`Logger::log`] for relevant
-    /// documentation.
-    pub fn add_log<T: Debug>(mut self, message: T, surround: Option<u32>) -> Self {
-        let surround = Some(surround.unwrap_or(self.surround));
+    /// Sets the minimum [`Level`] for the logs in this batch, overriding the
+    /// global minimum level set by [`set_min_level`] or `CODECTRL_LEVEL`.
+    pub fn min_level(mut self, level: Level) -> Self {
+        self.min_level = Some(level);
+        self
+    }
 
-        self.function_name_occurences.insert("add_log");
+    /// Sets how the logs of this batch are delivered to the server. Defaults to
+    /// [`DeliveryMode::Stream`].
+    pub fn delivery_mode(mut self, mode: DeliveryMode) -> Self {
+        self.delivery_mode = mode;
+        self
+    }
 
-        self.log_batch.push_back(create_log(
-            message,
-            surround,
-            Some("add_log"),
-            Some(&self.function_name_occurences),
-        ));
+    /// Also sends every log of this batch to the server at `host` and `port`,
+    /// i.e. a shared server as well as a local one. Can be called several
+    /// times to add more endpoints.
+    ///
+    /// Each endpoint is sent the batch at the same time over its own
+    /// connection, and keeps the logs that it failed to receive, which are
+    /// sent to it again with the next batch, up to
+    /// [`max_batch_bytes`](Limits::max_batch_bytes), after which it drops the
+    /// newer logs. The result of each endpoint, and how many logs it dropped,
+    /// is in [`BatchReport::fan_out`].
+    ///
+    /// ```no_run
+    /// use codectrl::Logger;
+    ///
+    /// # fn main() -> Result<(), codectrl::LoggerError> {
+    /// let mut batch = Logger::start_batch().fan_out("10.0.0.5", "3002");
+    ///
+    /// batch.push("Sent to 127.0.0.1 and 10.0.0.5", None);
+    ///
+    /// let report = batch.send_reported()?;
+    ///
+    /// for endpoint in report.fan_out() {
+    ///     println!(
+    ///         "{}: {} failed",
+    ///         endpoint.host(),
+    ///         endpoint.report().failed().len()
+    ///     );
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fan_out(mut self, host: &'static str, port: &'static str) -> Self {
+        self.fan_out.push(FanOut::new(host, port));
+        self
+    }
 
+    /// Adds a server to fail over to if `host` and `port`, and the servers
+    /// added before this one, can't be connected to. Can be called several
+    /// times to add more servers, which are tried in the order that they were
+    /// added.
+    ///
+    /// The server that was failed over to is remembered, and used first, until
+    /// the [cool-down](Self::failover_cooldown) is over, after which `host` is
+    /// tried again. Failover only applies to `host`, not to the endpoints that
+    /// the batch [fans out](Self::fan_out) to.
+    ///
+    /// ```no_run
+    /// use codectrl::Logger;
+    ///
+    /// # fn main() -> Result<(), codectrl::LoggerError> {
+    /// let mut batch = Logger::start_batch()
+    ///     .failover("10.0.0.5", "3002")
+    ///     .failover("10.0.0.6", "3002");
+    ///
+    /// // Sent to 10.0.0.5 if 127.0.0.1 can't be connected to.
+    /// batch.push("Hello", None);
+    /// batch.send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn failover(mut self, host: &'static str, port: &'static str) -> Self {
+        self.failover.push((host, port));
         self
     }
 
-    /// Batch equivelent of [`Logger::log_if`]. See [`Logger::log_if`] for
+    /// Sets how long a server that was failed over to is used before `host` is
+    /// tried again. Defaults to [`DEFAULT_FAILOVER_COOLDOWN`].
+    pub fn failover_cooldown(mut self, cooldown: Duration) -> Self {
+        self.failover_cooldown = cooldown;
+        self
+    }
+
+    /// Sets how long connecting to each server may take before the next
+    /// [failover](Self::failover) server is tried, or sending to it fails. Also
+    /// applies to the servers that the batch [fans out](Self::fan_out) to.
+    /// Defaults to [`DEFAULT_CONNECT_TIMEOUT`].
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// Sets the size [`Limits`] for the logs in this batch, overriding the
+    /// global limits set by [`set_limits`].
+    ///
+    /// Once `max_batch_bytes` is reached, the message of the next log is
+    /// truncated to fit into what is left of the limit, and any logs after that
+    /// are dropped. The last log in the batch will carry a warning with the
+    /// number of dropped logs.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Batch equivelent of [`Logger::log`]. See [`Logger::log`] for relevant
+    /// documentation.
+    #[track_caller]
+    pub fn add_log<T: Debug>(self, message: T, surround: Option<u32>) -> Self {
+        self.add(Level::Info, "add_log", true, message, surround)
+    }
+
+    /// Batch equivelent of [`Logger::log_at`]. See [`Logger::log_at`] for
     /// relevant documentation.
-    pub fn add_log_if<T: Debug>(
-        mut self,
-        condition: fn() -> bool,
+    #[track_caller]
+    pub fn add_log_at<T: Debug>(
+        self,
+        level: Level,
         message: T,
         surround: Option<u32>,
     ) -> Self {
-        let surround = Some(surround.unwrap_or(self.surround));
+        self.add(level, "add_log_at", true, message, surround)
+    }
 
-        self.function_name_occurences.insert("add_log_if");
+    /// Batch equivelent of [`Logger::log_with`]. See [`Logger::log_with`] for
+    /// relevant documentation.
+    #[track_caller]
+    pub fn add_log_with<T: Debug>(
+        self,
+        fields: impl IntoIterator<Item = Field>,
+        message: T,
+        surround: Option<u32>,
+    ) -> Self {
+        self.add_message(
+            Level::Info,
+            "add_log_with",
+            true,
+            || Message::debug(&message).with_fields(fields),
+            surround,
+        )
+    }
 
-        if condition() {
-            self.log_batch.push_back(create_log(
-                message,
-                surround,
-                Some("add_log_if"),
-                Some(&self.function_name_occurences),
-            ));
-        }
+    /// Batch equivelent of [`Logger::log_serialized`]. See
+    /// [`Logger::log_serialized`] for relevant documentation.
+    #[track_caller]
+    pub fn add_log_serialized<T: Serialize>(
+        self,
+        message: T,
+        surround: Option<u32>,
+    ) -> Self {
+        self.add_message(
+            Level::Info,
+            "add_log_serialized",
+            true,
+            || Message::serialized(&message),
+            surround,
+        )
+    }
 
-        self
+    /// Batch equivelent of [`Logger::log_limited`]. See
+    /// [`Logger::log_limited`] for relevant documentation.
+    #[track_caller]
+    pub fn add_log_limited<T: Debug>(
+        self,
+        limit: RateLimit,
+        message: T,
+        surround: Option<u32>,
+    ) -> Self {
+        self.add_limited(Level::Info, "add_log_limited", limit, message, surround)
     }
 
-    /// Batch equivelent of [`Logger::boxed_log_if`]. See
-    /// [`Logger::boxed_log_if`] for relevant documentation.
-    pub fn add_boxed_log_if<T: Debug>(
-        mut self,
-        condition: Box<dyn FnOnce() -> bool>,
+    /// Batch equivelent of [`Logger::log_limited_at`]. See
+    /// [`Logger::log_limited_at`] for relevant documentation.
+    #[track_caller]
+    pub fn add_log_limited_at<T: Debug>(
+        self,
+        level: Level,
+        limit: RateLimit,
         message: T,
         surround: Option<u32>,
     ) -> Self {
-        let surround = Some(surround.unwrap_or(self.surround));
+        self.add_limited(level, "add_log_limited_at", limit, message, surround)
+    }
 
-        self.function_name_occurences.insert("add_boxed_log_if");
+    /// Batch equivelent of [`Logger::log_suppressed_summary`]. See
+    /// [`Logger::log_suppressed_summary`] for relevant documentation.
+    #[track_caller]
+    pub fn add_suppressed_summary(self) -> Self {
+        let summary = match self.level_enabled(Level::Warn) {
+            true => rate_limit::suppressed_summary(),
+            false => BTreeMap::new(),
+        };
+        let len = self.log_batch.len();
+
+        let batch = self.add(
+            Level::Warn,
+            "add_suppressed_summary",
+            !summary.is_empty(),
+            rate_limit::summary_message(&summary),
+            None,
+        );
 
-        if condition() {
-            self.log_batch.push_back(create_log(
-                message,
-                surround,
-                Some("add_boxed_log_if"),
-                Some(&self.function_name_occurences),
-            ));
+        // The counts are kept for the next summary if it was filtered out.
+        if batch.log_batch.len() > len {
+            rate_limit::mark_reported(&summary);
         }
 
-        self
+        batch
+    }
+
+    /// Batch equivelent of [`Logger::log_if`]. See [`Logger::log_if`] for
+    /// relevant documentation.
+    #[track_caller]
+    pub fn add_log_if<T: Debug>(
+        self,
+        condition: impl Condition,
+        message: T,
+        surround: Option<u32>,
+    ) -> Self {
+        self.add_if(Level::Info, "add_log_if", condition, message, surround)
+    }
+
+    /// Batch equivelent of [`Logger::log_if_at`]. See [`Logger::log_if_at`]
+    /// for relevant documentation.
+    #[track_caller]
+    pub fn add_log_if_at<T: Debug>(
+        self,
+        level: Level,
+        condition: impl Condition,
+        message: T,
+        surround: Option<u32>,
+    ) -> Self {
+        self.add_if(level, "add_log_if_at", condition, message, surround)
     }
 
     /// Batch equivelent of [`Logger::log_when_env`]. See
     /// [`Logger::log_when_env`] for relevant documentation.
-    pub fn add_log_when_env<T: Debug>(
-        mut self,
+    #[track_caller]
+    pub fn add_log_when_env<T: Debug>(self, message: T, surround: Option<u32>) -> Self {
+        self.add_when_env(Level::Info, "add_log_when_env", message, surround)
+    }
+
+    /// Batch equivelent of [`Logger::log_when_env_at`]. See
+    /// [`Logger::log_when_env_at`] for relevant documentation.
+    #[track_caller]
+    pub fn add_log_when_env_at<T: Debug>(
+        self,
+        level: Level,
         message: T,
         surround: Option<u32>,
     ) -> Self {
-        let surround = Some(surround.unwrap_or(self.surround));
-
-        self.function_name_occurences.insert("add_log_when_env");
+        self.add_when_env(level, "add_log_when_env_at", message, surround)
+    }
 
-        if env::var("CODECTRL_DEBUG").ok().is_some() {
-            self.log_batch.push_back(create_log(
-                message,
-                surround,
-                Some("add_log_when_env"),
-                Some(&self.function_name_occurences),
-            ));
+    #[track_caller]
+    fn add_limited<T: Debug>(
+        self,
+        level: Level,
+        function_name: &'static str,
+        limit: RateLimit,
+        message: T,
+        surround: Option<u32>,
+    ) -> Self {
+        let suppressed = if admit(self.level_enabled(level)) {
+            rate_limit::check(&limit, Location::caller())
         } else {
-            #[cfg(debug_assertions)]
-            println!("add_log_when_env not called: envvar CODECTRL_DEBUG not present");
+            None
+        };
+
+        let mut batch = self.add(
+            level,
+            function_name,
+            suppressed.is_some(),
+            message,
+            surround,
+        );
+
+        if let (Some(suppressed @ 1..), Some(log)) =
+            (suppressed, batch.log_batch.back_mut())
+        {
+            log.warnings
+                .push(Warning::Suppressed(suppressed).to_string());
         }
 
-        self
+        batch
     }
 
-    /// Consumes `self` and returns a [`Logger`] that can be used to send
-    /// multiple [`Log`]s with one gRPC connection.
-    ///
-    /// [`Log`]: codectrl_protobuf_bindings::data::Log
-    pub fn build(mut self) -> Logger<'a> {
-        self.logger = Logger {
-            log_batch: self.log_batch,
-            batch_host: self.host,
-            batch_port: self.port,
-            batch_tokio_runtime: self.tokio_runtime,
-        };
+    #[track_caller]
+    fn add_if<T: Debug>(
+        self,
+        level: Level,
+        function_name: &'static str,
+        condition: impl Condition,
+        message: T,
+        surround: Option<u32>,
+    ) -> Self {
+        // The condition is only evaluated if the level would be sent anyway.
+        let should_log = admit(self.level_enabled(level) && condition.check());
 
-        self.logger
+        self.add(level, function_name, should_log, message, surround)
     }
-}
 
-/// The main type to be used to create and send [`Log`]s to a specified gRPC
-/// server. This is the main "entrypoint" for any usage of this crate.
-///
-/// [`Log`]: codectrl_protobuf_bindings::data::Log
-#[derive(Debug, Clone, Default)]
-pub struct Logger<'a> {
-    log_batch: VecDeque<Log>,
-    batch_host: &'static str,
-    batch_port: &'static str,
-    batch_tokio_runtime: Option<&'a Handle>,
-}
+    #[track_caller]
+    fn add_when_env<T: Debug>(
+        self,
+        level: Level,
+        function_name: &'static str,
+        message: T,
+        surround: Option<u32>,
+    ) -> Self {
+        if !admit(self.level_enabled(level)) {
+            return self.add(level, function_name, false, message, surround);
+        }
 
-impl<'a> Logger<'a> {
-    /// Returns a [`LogBatch`], which can be used to start the process of
-    /// generating multiple logs to be sent in a single connection. Should
-    /// be preferred over sending one-time [`Log`]s if possile.
-    ///
-    /// [`Log`]: codectrl_protobuf_bindings::data::Log
-    pub fn start_batch() -> LogBatch<'a> { LogBatch::new(Self::default()) }
+        let env_present = env::var("CODECTRL_DEBUG").ok().is_some();
 
-    /// Sends the configured batch in `log_batch` to the configured `batch_host`
-    /// and `batch_port`. This _should_ be the preferred way of sending
-    /// multiple logs.
-    ///
-    /// If given a pre-existing tokio runtime, it _will_ block the executor
-    /// while it waits for the log to complete.
-    pub fn send_batch(&mut self) -> LoggerResult<()> {
-        if self.log_batch.is_empty() {
-            return Err(LoggerError::LoggerError(
-                "Can't send batch: Log batch is empty".to_string(),
-            ));
+        if !env_present {
+            #[cfg(debug_assertions)]
+            diagnostics::warn(
+                "envvar CODECTRL_DEBUG not present",
+                format_args!("{function_name} not called"),
+            );
         }
 
-        let mut ret = Ok(());
-
-        async fn send_batch(
-            host: &str,
-            port: &str,
-            logs: &mut VecDeque<Log>,
-        ) -> LoggerResult<()> {
-            let mut log_client =
-                LoggerClient::connect(format!("http://{host}:{port}")).await?;
-
-            let request = Request::new(stream::iter(logs.clone()));
-            let response = log_client.send_logs(request).await?;
-
-            match response.into_inner() {
-                RequestResult { status, .. }
-                    if status == RequestStatus::Confirmed.into() =>
-                    Ok(()),
-                RequestResult {
-                    message,
-                    status,
-                    auth_status,
-                } if status == RequestStatus::Error.into() => Err(RequestResult {
-                    message,
-                    status,
-                    auth_status,
-                }
-                .into()),
-                RequestResult { .. } => unreachable!(),
-            }
-        }
+        self.add(level, function_name, admit(env_present), message, surround)
+    }
 
-        if let Some(handle) = self.batch_tokio_runtime {
-            handle.block_on(async {
-                ret = send_batch(self.batch_host, self.batch_port, &mut self.log_batch)
-                    .await;
+    #[track_caller]
+    fn add<T: Debug>(
+        self,
+        level: Level,
+        function_name: &'static str,
+        should_log: bool,
+        message: T,
+        surround: Option<u32>,
+    ) -> Self {
+        self.add_message(
+            level,
+            function_name,
+            should_log,
+            || Message::debug(&message),
+            surround,
+        )
+    }
+
+    // The occurence of `function_name` is always recorded, even if the log is
+    // not added, so that the line numbers of the following `add_X` calls can
+    // still be found by `Logger::get_code_snippet`.
+    #[track_caller]
+    fn add_message(
+        mut self,
+        level: Level,
+        function_name: &'static str,
+        should_log: bool,
+        message: impl FnOnce() -> Message,
+        surround: Option<u32>,
+    ) -> Self {
+        self.push_message(level, Some(function_name), should_log, message, surround);
+        self
+    }
+
+    // If `function_name` is `None`, the log is not assumed to be part of a
+    // chain of `add_X` calls and its line number is taken from the backtrace.
+    #[track_caller]
+    fn push_message(
+        &mut self,
+        level: Level,
+        function_name: Option<&'static str>,
+        should_log: bool,
+        message: impl FnOnce() -> Message,
+        surround: Option<u32>,
+    ) {
+        if let Some(log) =
+            self.create_message(level, function_name, should_log, message, surround)
+        {
+            self.push_within_limit(log);
+        }
+    }
+
+    // Creates the log for `push_message`, without adding it to the batch.
+    #[track_caller]
+    fn create_message(
+        &mut self,
+        level: Level,
+        function_name: Option<&'static str>,
+        should_log: bool,
+        message: impl FnOnce() -> Message,
+        surround: Option<u32>,
+    ) -> Option<Log> {
+        let surround = Some(surround.unwrap_or(self.surround));
+
+        if let Some(function_name) = function_name {
+            self.function_name_occurences.insert(function_name);
+        }
+
+        if !(should_log && admit(self.level_enabled(level))) {
+            return None;
+        }
+
+        create_log(
+            level,
+            message(),
+            &self.limits,
+            surround,
+            function_name,
+            Some(&self.function_name_occurences),
+        )
+    }
+
+    // Whether `log` can be added without going over `max_batch_bytes`.
+    fn fits(&self, log: &Log) -> bool {
+        self.limits.max_batch_bytes.is_none_or(|max_batch_bytes| {
+            self.batch_bytes + limits::log_size(log) <= max_batch_bytes
+        })
+    }
+
+    fn push_within_limit(&mut self, mut log: Log) {
+        if let Some(max_batch_bytes) = self.limits.max_batch_bytes {
+            let remaining = max_batch_bytes.saturating_sub(self.batch_bytes);
+            let overflow = limits::log_size(&log).saturating_sub(remaining);
+
+            if overflow > 0 {
+                // Only truncate if a useful amount of the message would be left.
+                let max_message_bytes = log.message.len().saturating_sub(overflow);
+
+                if self.dropped > 0 || max_message_bytes < limits::MIN_TRUNCATED_BYTES {
+                    self.dropped += 1;
+                    stats::add(Counter::Dropped, 1);
+                    return;
+                }
+
+                if let Some(size) = limits::truncate(&mut log.message, max_message_bytes)
+                {
+                    unmark_json(&mut log.message_type);
+                    log.warnings
+                        .push(Warning::TruncatedForBatch(size).to_string());
+                }
+            }
+        }
+
+        self.batch_bytes += limits::log_size(&log);
+        self.log_batch.push_back(log);
+    }
+
+    fn level_enabled(&self, level: Level) -> bool {
+        match self.min_level {
+            Some(min_level) => !cfg!(feature = "disabled") && level >= min_level,
+            None => level.is_enabled(),
+        }
+    }
+
+    /// Adds a log with [`Level::Info`] through `&mut self`, so that the batch
+    /// can be kept around and reused between sends instead of being rebuilt.
+    ///
+    /// ```no_run
+    /// use codectrl::Logger;
+    ///
+    /// # fn main() -> Result<(), codectrl::LoggerError> {
+    /// let mut batch = Logger::start_batch();
+    ///
+    /// for chunk in [[1, 2], [3, 4]] {
+    ///     batch.extend(chunk, None);
+    ///     batch.push("chunk done", None);
+    ///
+    ///     // Sent logs are removed, so the next chunk starts empty.
+    ///     batch.send()?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn push<T: Debug>(&mut self, message: T, surround: Option<u32>) -> &mut Self {
+        self.push_at(Level::Info, message, surround)
+    }
+
+    /// Same as [`Self::push`], but with the given [`Level`].
+    #[track_caller]
+    pub fn push_at<T: Debug>(
+        &mut self,
+        level: Level,
+        message: T,
+        surround: Option<u32>,
+    ) -> &mut Self {
+        self.push_message(level, None, true, || Message::debug(&message), surround);
+        self
+    }
+
+    /// Adds a log with [`Level::Info`] for each of `messages`.
+    #[track_caller]
+    pub fn extend<T: Debug>(
+        &mut self,
+        messages: impl IntoIterator<Item = T>,
+        surround: Option<u32>,
+    ) -> &mut Self {
+        for message in messages {
+            self.push_at(Level::Info, message, surround);
+        }
+
+        self
+    }
+
+    /// The number of logs waiting to be sent.
+    pub fn len(&self) -> usize { self.log_batch.len() }
+
+    /// Whether there are no logs waiting to be sent.
+    pub fn is_empty(&self) -> bool { self.log_batch.is_empty() }
+
+    /// Removes every log from the batch without sending them.
+    pub fn clear(&mut self) {
+        self.log_batch.clear();
+        self.retained = 0;
+        self.batch_bytes = 0;
+        self.dropped = 0;
+    }
+
+    /// Removes every log from the batch and returns them, i.e. to send them
+    /// in some other way.
+    pub fn drain(&mut self) -> vec_deque::Drain<'_, Log> {
+        self.retained = 0;
+        self.batch_bytes = 0;
+        self.dropped = 0;
+        self.log_batch.drain(..)
+    }
+
+    /// Sends every log in the batch and removes them once they were sent, so
+    /// that the batch can be reused. Does nothing if the batch is empty.
+    ///
+    /// If sending fails, the logs that were not delivered are kept in the batch
+    /// so that they are sent with the next call. See [`Self::send_reported`]
+    /// to find out which logs those are.
+    pub fn send(&mut self) -> LoggerResult<()> { self.send_reported()?.into_result() }
+
+    /// Same as [`Self::send`], but returns a [`BatchReport`] of which logs were
+    /// accepted and which failed, rather than only the first error.
+    ///
+    /// With [`DeliveryMode::Stream`], which is the default, either every log
+    /// is accepted or every log fails. Use [`Self::delivery_mode`] with
+    /// [`DeliveryMode::PerLog`] to get the result of each log.
+    pub fn send_reported(&mut self) -> LoggerResult<BatchReport> {
+        if self.nothing_to_send() {
+            return Ok(BatchReport::default());
+        }
+
+        let mut logger = self.take_logger();
+        let report = logger.send_batch_reported();
+
+        self.restore_logger(logger);
+
+        report
+    }
+
+    /// An async version of [`Self::send`], see [`Logger::send_batch_async`].
+    pub async fn send_async(&mut self) -> LoggerResult<()> {
+        self.send_reported_async().await?.into_result()
+    }
+
+    /// An async version of [`Self::send_reported`], see
+    /// [`Logger::send_batch_async`].
+    pub async fn send_reported_async(&mut self) -> LoggerResult<BatchReport> {
+        if self.nothing_to_send() {
+            return Ok(BatchReport::default());
+        }
+
+        let mut logger = self.take_logger();
+        let report = logger.send_batch_reported_async().await;
+
+        self.restore_logger(logger);
+
+        report
+    }
+
+    // Whether there are no logs in the batch, nor in the backlogs of the
+    // endpoints that it fans out to.
+    fn nothing_to_send(&self) -> bool {
+        self.is_empty() && self.fan_out.iter().all(|e| e.backlog.is_empty())
+    }
+
+    // Keeps the logs that the logger taken by `take_logger` failed to send, in
+    // front of any that were added while it was sending them.
+    fn restore_logger(&mut self, logger: Logger<'_>) {
+        let mut log_batch = logger.log_batch;
+
+        log_batch.append(&mut self.log_batch);
+
+        self.batch_bytes = log_batch.iter().map(limits::log_size).sum();
+        self.log_batch = log_batch;
+        self.retained = logger.batch_retained;
+        self.fan_out = logger.batch_fan_out;
+    }
+
+    /// Consumes `self` and returns a [`Logger`] that can be used to send
+    /// multiple [`Log`]s with one gRPC connection.
+    ///
+    /// [`Log`]: codectrl_protobuf_bindings::data::Log
+    pub fn build(mut self) -> Logger<'a> {
+        self.logger = self.take_logger();
+        self.logger
+    }
+
+    /// Turns this batch into an [`AutoFlushBatch`], which sends its logs by
+    /// itself once it reaches a configured number of logs, size or age, and
+    /// when it is dropped.
+    pub fn auto_flush(self) -> AutoFlushBatch<'a> { AutoFlushBatch::new(self) }
+
+    // Separates the runtime from this batch, so that the batch can be moved to a
+    // task and sent without it.
+    fn into_static(self) -> (LogBatch<'static>, Option<&'a Handle>) {
+        let batch = LogBatch {
+            logger: Logger::default(),
+            log_batch: self.log_batch,
+            tokio_runtime: None,
+            host: self.host,
+            port: self.port,
+            surround: self.surround,
+            min_level: self.min_level,
+            limits: self.limits,
+            delivery_mode: self.delivery_mode,
+            fan_out: self.fan_out,
+            failover: self.failover,
+            failover_cooldown: self.failover_cooldown,
+            connect_timeout: self.connect_timeout,
+            retained: self.retained,
+            batch_bytes: self.batch_bytes,
+            dropped: self.dropped,
+            function_name_occurences: self.function_name_occurences,
+        };
+
+        (batch, self.tokio_runtime)
+    }
+
+    // Moves the logs collected so far into a `Logger`, leaving this batch empty
+    // so that it can be reused.
+    fn take_logger(&mut self) -> Logger<'a> { self.take_logger_on(self.tokio_runtime) }
+
+    // `take_logger`, with a logger that sends the logs on `tokio_runtime`
+    // rather than the runtime of this batch.
+    fn take_logger_on<'b>(&mut self, tokio_runtime: Option<&'b Handle>) -> Logger<'b> {
+        if let (1.., Some(log)) = (self.dropped, self.log_batch.back_mut()) {
+            log.warnings
+                .push(Warning::BatchLimitReached(self.dropped).to_string());
+        }
+
+        self.batch_bytes = 0;
+        self.dropped = 0;
+
+        Logger {
+            log_batch: std::mem::take(&mut self.log_batch),
+            batch_host: self.host,
+            batch_port: self.port,
+            batch_tokio_runtime: tokio_runtime,
+            batch_delivery_mode: self.delivery_mode,
+            batch_fan_out: std::mem::take(&mut self.fan_out),
+            batch_failover: self.failover.clone(),
+            batch_failover_cooldown: self.failover_cooldown,
+            batch_connect_timeout: self.connect_timeout,
+            batch_retained: std::mem::take(&mut self.retained),
+            batch_max_bytes: self.limits.max_batch_bytes,
+        }
+    }
+}
+
+/// The main type to be used to create and send [`Log`]s to a specified gRPC
+/// server. This is the main "entrypoint" for any usage of this crate.
+///
+/// [`Log`]: codectrl_protobuf_bindings::data::Log
+#[derive(Debug, Clone, Default)]
+// The batch settings are only read when sending, which the `disabled` feature
+// compiles out.
+#[cfg_attr(feature = "disabled", allow(dead_code))]
+pub struct Logger<'a> {
+    log_batch: VecDeque<Log>,
+    batch_host: &'static str,
+    batch_port: &'static str,
+    batch_tokio_runtime: Option<&'a Handle>,
+    batch_delivery_mode: DeliveryMode,
+    batch_fan_out: Vec<FanOut>,
+    batch_failover: Vec<(&'static str, &'static str)>,
+    batch_failover_cooldown: Duration,
+    batch_connect_timeout: Duration,
+    batch_retained: usize,
+    batch_max_bytes: Option<usize>,
+}
+
+impl<'a> Logger<'a> {
+    /// Returns a [`LogBatch`], which can be used to start the process of
+    /// generating multiple logs to be sent in a single connection. Should
+    /// be preferred over sending one-time [`Log`]s if possile.
+    ///
+    /// [`Log`]: codectrl_protobuf_bindings::data::Log
+    pub fn start_batch() -> LogBatch<'a> { LogBatch::new(Self::default()) }
+
+    /// Returns a snapshot of the counters of the logger, i.e. how many logs
+    /// were sent, failed or were dropped, to find out whether logs are being
+    /// lost.
+    ///
+    /// ```no_run
+    /// use codectrl::Logger;
+    ///
+    /// let stats = Logger::stats();
+    ///
+    /// println!(
+    ///     "{} sent, {} failed, {} filtered",
+    ///     stats.logs_sent, stats.logs_failed, stats.logs_filtered
+    /// );
+    /// ```
+    pub fn stats() -> Stats { stats::snapshot() }
+
+    /// Checks whether the server at `host` and `port` can be connected to,
+    /// without sending a log, i.e. to tell the user at startup rather than
+    /// when the first log fails. Gives up after [`CONNECTION_CHECK_TIMEOUT`].
+    ///
+    /// ```no_run
+    /// use codectrl::Logger;
+    ///
+    /// let check = Logger::check_connection(None, None, None);
+    ///
+    /// if !check.is_reachable() {
+    ///     // "CodeCTRL not reachable at 127.0.0.1:3002: ..."
+    ///     eprintln!("{check}");
+    /// }
+    /// ```
+    ///
+    /// If given a pre-existing tokio runtime, it _will_ block the executor
+    /// while it waits for the connection.
+    pub fn check_connection(
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> ConnectionCheck {
+        let host = host.unwrap_or("127.0.0.1").to_string();
+        let port = port.unwrap_or("3002").to_string();
+
+        #[cfg(feature = "disabled")]
+        {
+            let _ = tokio_runtime;
+
+            ConnectionCheck::failed(host, port, disabled())
+        }
+
+        #[cfg(not(feature = "disabled"))]
+        {
+            let check = runtime::block_on(tokio_runtime, || {
+                transport::check(host.clone(), port.clone())
             });
-        } else {
-            let rt = Runtime::new()?;
 
-            rt.block_on(async {
-                ret = send_batch(self.batch_host, self.batch_port, &mut self.log_batch)
-                    .await;
-            })
+            check.unwrap_or_else(|e| ConnectionCheck::failed(host, port, e))
         }
+    }
+
+    /// An async version of [`Self::check_connection`], that can be awaited on
+    /// any executor, see [`Self::log_async`].
+    pub fn check_connection_async(
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = ConnectionCheck> + Send + 'static {
+        let host = host.unwrap_or("127.0.0.1").to_string();
+        let port = port.unwrap_or("3002").to_string();
+
+        #[cfg(feature = "disabled")]
+        let task = {
+            let _ = tokio_runtime;
+
+            Err::<std::future::Ready<_>, _>(disabled())
+        };
+
+        #[cfg(not(feature = "disabled"))]
+        let task = {
+            let check = transport::check(host.clone(), port.clone());
+
+            runtime::spawn(tokio_runtime, move || check)
+        };
+
+        async move {
+            let check = match task {
+                Ok(task) => task.await,
+                Err(e) => Err(e),
+            };
+
+            check.unwrap_or_else(|e| ConnectionCheck::failed(host, port, e))
+        }
+    }
+
+    /// Sends the configured batch in `log_batch` to the configured `batch_host`
+    /// and `batch_port`. This _should_ be the preferred way of sending
+    /// multiple logs.
+    ///
+    /// The logs are removed once they were sent, so calling this again does
+    /// not send them twice. If sending fails, they are kept.
+    ///
+    /// If given a pre-existing tokio runtime, it _will_ block the executor
+    /// while it waits for the log to complete.
+    pub fn send_batch(&mut self) -> LoggerResult<()> {
+        self.send_batch_reported()?.into_result()
+    }
+
+    /// Same as [`Self::send_batch`], but returns a [`BatchReport`] of which
+    /// logs were accepted and which failed, rather than only the first error.
+    /// The logs that failed are kept in the batch.
+    ///
+    /// Only returns an error if the batch is empty or the shared tokio runtime
+    /// could not be created, in which case nothing was sent.
+    pub fn send_batch_reported(&mut self) -> LoggerResult<BatchReport> {
+        #[cfg(feature = "disabled")]
+        return Ok(BatchReport::default());
+
+        #[cfg(not(feature = "disabled"))]
+        {
+            self.check_batch()?;
+
+            let send = runtime::block_on(self.batch_tokio_runtime, || self.take_send())?;
+
+            Ok(self.restore_send(send))
+        }
+    }
+
+    /// An async version of [`Self::send_batch`], that can be awaited on any
+    /// executor, i.e. `async-std` or `smol`.
+    ///
+    /// The batch is sent on the configured tokio runtime, or on the executor
+    /// that is shared by the crate, see the `rt-tokio` and `rt-async-std`
+    /// features. Awaiting it doesn't block the executor.
+    ///
+    /// If the future is dropped before it completes, the batch is still sent,
+    /// but the logs that failed are not kept.
+    pub async fn send_batch_async(&mut self) -> LoggerResult<()> {
+        self.send_batch_reported_async().await?.into_result()
+    }
+
+    /// An async version of [`Self::send_batch_reported`], see
+    /// [`Self::send_batch_async`].
+    pub async fn send_batch_reported_async(&mut self) -> LoggerResult<BatchReport> {
+        #[cfg(feature = "disabled")]
+        return Ok(BatchReport::default());
+
+        #[cfg(not(feature = "disabled"))]
+        {
+            self.check_batch()?;
+
+            let task = runtime::spawn(self.batch_tokio_runtime, || self.take_send())?;
+
+            Ok(self.restore_send(task.await?))
+        }
+    }
+
+    #[cfg(not(feature = "disabled"))]
+    fn check_batch(&self) -> LoggerResult<()> {
+        if self.log_batch.is_empty()
+            && self.batch_fan_out.iter().all(|e| e.backlog.is_empty())
+        {
+            return Err(LoggerError::LoggerError(
+                "Can't send batch: Log batch is empty".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Takes the logs out of the batch and returns the future that sends them,
+    // which is given back to `restore_send` once it completes.
+    #[cfg(not(feature = "disabled"))]
+    fn take_send(
+        &mut self,
+    ) -> impl Future<Output = (BatchReport, VecDeque<Log>, Vec<FanOut>)> + Send + 'static
+    {
+        let endpoints = Endpoints::new(self.batch_host, self.batch_port)
+            .failover(
+                self.batch_failover.iter().copied(),
+                self.batch_failover_cooldown,
+            )
+            .connect_timeout(self.batch_connect_timeout);
+
+        fan_out::send(
+            endpoints,
+            self.batch_delivery_mode,
+            mem::take(&mut self.log_batch),
+            self.batch_retained,
+            self.batch_max_bytes,
+            mem::take(&mut self.batch_fan_out),
+        )
+    }
+
+    // Keeps the logs that failed, so that they are sent with the next batch.
+    #[cfg(not(feature = "disabled"))]
+    fn restore_send(
+        &mut self,
+        (report, failed, fan_out): (BatchReport, VecDeque<Log>, Vec<FanOut>),
+    ) -> BatchReport {
+        self.batch_retained = failed.len();
+        self.log_batch = failed;
+        self.batch_fan_out = fan_out;
+
+        report
+    }
+
+    // Returns the logs that failed, which are all of them unless they were
+    // accepted.
+    #[cfg(not(feature = "disabled"))]
+    async fn _send_batch(
+        endpoints: Endpoints,
+        mode: DeliveryMode,
+        mut logs: VecDeque<Log>,
+    ) -> (BatchReport, VecDeque<Log>) {
+        let mut report = BatchReport::default();
+        let start = Instant::now();
+
+        let mut log_client = match transport::connect_any(&endpoints).await {
+            Ok(log_client) => log_client,
+            Err(e) => {
+                stats::record_send(start, false, &logs);
+                report.fail_all(logs.len(), e);
+                return (report, logs);
+            },
+        };
+
+        match mode {
+            DeliveryMode::Stream => {
+                let request = Request::new(stream::iter(logs.clone()));
+
+                let result =
+                    match runtime::timeout(SEND_TIMEOUT, log_client.send_logs(request))
+                        .await
+                    {
+                        Ok(Ok(response)) => check_result(response.into_inner()),
+                        Ok(Err(e)) => Err(e.into()),
+                        Err(e) => Err(e),
+                    };
+
+                stats::record_send(start, result.is_ok(), &logs);
+
+                match result {
+                    Ok(()) => {
+                        report.accepted = (0..logs.len()).collect();
+                        logs.clear();
+                    },
+                    Err(e) => report.fail_all(logs.len(), e),
+                }
+            },
+            DeliveryMode::PerLog => {
+                let mut failed = VecDeque::new();
+
+                for (index, log) in mem::take(&mut logs).into_iter().enumerate() {
+                    // The first request also includes connecting.
+                    let start = if index == 0 { start } else { Instant::now() };
+                    let request = log_client.send_log(Request::new(log.clone()));
+                    let result = match runtime::timeout(SEND_TIMEOUT, request).await {
+                        Ok(Ok(response)) => check_result(response.into_inner()),
+                        Ok(Err(e)) => Err(e.into()),
+                        Err(e) => Err(e),
+                    };
+
+                    stats::record_send(start, result.is_ok(), [&log]);
+
+                    match result {
+                        Ok(()) => report.accepted.push(index),
+                        Err(e) => {
+                            report.failed.push(FailedLog {
+                                index,
+                                error: Arc::new(e),
+                            });
+                            failed.push_back(log);
+                        },
+                    }
+                }
+
+                logs = failed;
+            },
+        }
+
+        (report, logs)
+    }
+
+    /// The main log function that is called from Rust code. Logs are sent with
+    /// [`Level::Info`], see [`Self::log_at`] to specify a different level.
+    ///
+    /// If the caller was compiled without debug info, the log has no stack
+    /// trace and points at the line that called this function instead.
+    ///
+    /// If given a pre-existing tokio runtime, it _will_ block the executor
+    /// while it waits for the log to complete.
+    #[track_caller]
+    pub fn log<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<()> {
+        Self::log_at(Level::Info, message, surround, host, port, tokio_runtime)
+    }
+
+    /// A variation of [`Self::log`] that sends the log with the given
+    /// [`Level`].
+    ///
+    /// If `level` is below the global minimum level (see [`set_min_level`]),
+    /// the log is dropped before any backtrace is captured and nothing is
+    /// sent.
+    ///
+    /// If given a pre-existing tokio runtime, it _will_ block the executor
+    /// while it waits for the log to complete.
+    #[track_caller]
+    pub fn log_at<T: Debug>(
+        level: Level,
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<()> {
+        match Self::new_log(level, || Message::debug(&message), surround) {
+            Some(log) => Self::send_log(log, host, port, tokio_runtime),
+            None => Ok(()),
+        }
+    }
+
+    /// An async version of [`Self::log`], that can be awaited on any executor,
+    /// i.e. `async-std` or `smol`.
+    ///
+    /// The log is created when this is called, and sent on the given tokio
+    /// runtime, or on the executor that is shared by the crate, see the
+    /// `rt-tokio` and `rt-async-std` features. Awaiting it doesn't block the
+    /// executor, and the log is sent even if the future is dropped.
+    ///
+    /// ```no_run
+    /// use codectrl::Logger;
+    ///
+    /// # async fn handle_request() -> Result<(), codectrl::LoggerError> {
+    /// Logger::log_async("Handling request", None, None, None, None).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn log_async<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<()>> + Send + 'static {
+        Self::log_at_async(Level::Info, message, surround, host, port, tokio_runtime)
+    }
+
+    /// An async version of [`Self::log_at`], see [`Self::log_async`].
+    #[track_caller]
+    pub fn log_at_async<T: Debug>(
+        level: Level,
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<()>> + Send + 'static {
+        let log = Self::new_log(level, || Message::debug(&message), surround);
+
+        Self::send_log_async(log, host, port, tokio_runtime)
+    }
+
+    /// A variation of [`Self::log`] that attaches structured [`Field`]s of
+    /// context to the log, without them being part of the message.
+    ///
+    /// The fields are sent as a JSON object on the last line of the `message`
+    /// of the [`Log`], see [`FIELDS_PREFIX`] for the exact format.
+    ///
+    /// ```no_run
+    /// # use codectrl::{fields, Logger};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let request_id = "a1b2";
+    ///
+    /// Logger::log_with(
+    ///     fields![request_id = request_id, attempt = 3],
+    ///     "Request failed",
+    ///     None,
+    ///     None,
+    ///     None,
+    ///     None,
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// If given a pre-existing tokio runtime, it _will_ block the executor
+    /// while it waits for the log to complete.
+    ///
+    /// [`Log`]: codectrl_protobuf_bindings::data::Log
+    #[track_caller]
+    pub fn log_with<T: Debug>(
+        fields: impl IntoIterator<Item = Field>,
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<()> {
+        let message = || Message::debug(&message).with_fields(fields);
+
+        match Self::new_log(Level::Info, message, surround) {
+            Some(log) => Self::send_log(log, host, port, tokio_runtime),
+            None => Ok(()),
+        }
+    }
+
+    /// An async version of [`Self::log_with`], see [`Self::log_async`].
+    #[track_caller]
+    pub fn log_with_async<T: Debug>(
+        fields: impl IntoIterator<Item = Field>,
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<()>> + Send + 'static {
+        let message = || Message::debug(&message).with_fields(fields);
+        let log = Self::new_log(Level::Info, message, surround);
+
+        Self::send_log_async(log, host, port, tokio_runtime)
+    }
+
+    /// A variation of [`Self::log`] for messages that implement [`Serialize`].
+    /// The message is sent as pretty-printed JSON rather than its [`Debug`]
+    /// representation, and the `message_type` of the [`Log`] is marked with
+    /// [`JSON_MESSAGE_TYPE_PREFIX`], i.e. `[INFO] json:my_crate::Request`, so
+    /// that front-ends and other tools can parse it.
+    ///
+    /// If the message fails to serialise, the log is still sent with a
+    /// warning containing the error.
+    ///
+    /// So that the message stays valid JSON, the active [`scope`]s are not
+    /// attached to it, and if it has to be truncated to fit into the
+    /// [`Limits`], it is no longer marked as JSON.
+    ///
+    /// If given a pre-existing tokio runtime, it _will_ block the executor
+    /// while it waits for the log to complete.
+    ///
+    /// [`Log`]: codectrl_protobuf_bindings::data::Log
+    #[track_caller]
+    pub fn log_serialized<T: Serialize>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<()> {
+        match Self::new_log(Level::Info, || Message::serialized(&message), surround) {
+            Some(log) => Self::send_log(log, host, port, tokio_runtime),
+            None => Ok(()),
+        }
+    }
+
+    /// An async version of [`Self::log_serialized`], see [`Self::log_async`].
+    #[track_caller]
+    pub fn log_serialized_async<T: Serialize>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<()>> + Send + 'static {
+        let log = Self::new_log(Level::Info, || Message::serialized(&message), surround);
 
-        ret
+        Self::send_log_async(log, host, port, tokio_runtime)
     }
 
-    /// The main log function that is called from Rust code.
+    /// A variation of [`Self::log`] that only sends the log if the
+    /// [`RateLimit`] for this callsite allows it. Useful for logging inside of
+    /// hot loops without flooding the server.
     ///
-    /// This function will print a warning to stderr if this crate is compiled
-    /// with debug_assertions disabled as it will produce a much less
-    /// informative log for codeCTRL.
+    /// The next log that is sent from the callsite will carry a warning with
+    /// the number of logs that were suppressed in the meantime. See
+    /// [`Self::log_suppressed_summary`] to report the suppressed logs of every
+    /// callsite.
     ///
     /// If given a pre-existing tokio runtime, it _will_ block the executor
     /// while it waits for the log to complete.
-    pub fn log<T: Debug>(
+    #[track_caller]
+    pub fn log_limited<T: Debug>(
+        limit: RateLimit,
         message: T,
         surround: Option<u32>,
         host: Option<&str>,
         port: Option<&str>,
         tokio_runtime: Option<&Handle>,
-    ) -> LoggerResult<()> {
-        let host = host.unwrap_or("127.0.0.1");
-        let port = port.unwrap_or("3002");
+    ) -> LoggerResult<bool> {
+        Self::log_limited_at(
+            Level::Info,
+            limit,
+            message,
+            surround,
+            host,
+            port,
+            tokio_runtime,
+        )
+    }
+
+    /// A variation of [`Self::log_limited`] that sends the log with the given
+    /// [`Level`]. The callsite is not counted towards its [`RateLimit`] if
+    /// `level` is below the global minimum level.
+    #[track_caller]
+    pub fn log_limited_at<T: Debug>(
+        level: Level,
+        limit: RateLimit,
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<bool> {
+        let Some(log) = Self::limited_log(level, &limit, &message, surround) else {
+            return Ok(false);
+        };
 
-        let mut log = create_log(message, surround, None, None);
+        Self::send_log(log, host, port, tokio_runtime)?;
 
-        let mut ret = Ok(());
+        Ok(true)
+    }
 
-        if let Some(handle) = tokio_runtime {
-            handle.block_on(async {
-                ret = Self::_log(&mut log, host, port).await;
-            });
-        } else {
-            let rt = Runtime::new()?;
+    /// An async version of [`Self::log_limited`], see [`Self::log_async`].
+    #[track_caller]
+    pub fn log_limited_async<T: Debug>(
+        limit: RateLimit,
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<bool>> + Send + 'static {
+        Self::log_limited_at_async(
+            Level::Info,
+            limit,
+            message,
+            surround,
+            host,
+            port,
+            tokio_runtime,
+        )
+    }
 
-            rt.block_on(async {
-                ret = Self::_log(&mut log, host, port).await;
-            })
-        }
+    /// An async version of [`Self::log_limited_at`], see [`Self::log_async`].
+    #[track_caller]
+    pub fn log_limited_at_async<T: Debug>(
+        level: Level,
+        limit: RateLimit,
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<bool>> + Send + 'static {
+        let log = Self::limited_log(level, &limit, &message, surround);
+        let sent = log.is_some();
+        let send = Self::send_log_async(log, host, port, tokio_runtime);
+
+        async move { send.await.map(|()| sent) }
+    }
+
+    /// Runs `f` and sends a log with how long it took, pointing at the line of
+    /// this call. The log is sent with the global logger, see
+    /// [`TimerGuard`] for its contents.
+    ///
+    /// ```no_run
+    /// use codectrl::Logger;
+    ///
+    /// let sum = Logger::time("sum", || (0..1_000_000u64).sum::<u64>());
+    /// ```
+    #[track_caller]
+    pub fn time<R>(label: impl Into<String>, f: impl FnOnce() -> R) -> R {
+        let _timer = Self::timer(label);
+
+        f()
+    }
+
+    /// Same as [`Self::time`], but only sends the log if `f` took at least
+    /// `threshold`, so that only slow executions are reported.
+    #[track_caller]
+    pub fn time_over<R>(
+        label: impl Into<String>,
+        threshold: Duration,
+        f: impl FnOnce() -> R,
+    ) -> R {
+        let _timer = Self::timer(label).threshold(threshold);
+
+        f()
+    }
 
-        ret
+    /// Returns a [`TimerGuard`], which sends a log with the time that has
+    /// elapsed once it is dropped, i.e. at the end of the current block.
+    #[track_caller]
+    pub fn timer(label: impl Into<String>) -> TimerGuard {
+        TimerGuard::new(label, Location::caller())
     }
 
-    /// A log function that takes a closure and only logs out if that function
-    /// returns `true`. Essentially a conditional wrapper over
-    /// [`Self::log`]. See [`Self::boxed_log_if`] for a variation that
-    /// allows for closures that take can take from values in scope.
+    /// Sends a single log with [`Level::Warn`] containing the number of logs
+    /// that were suppressed by a [`RateLimit`] at each callsite since the last
+    /// summary. Nothing is sent if no logs were suppressed.
+    ///
+    /// The counts are only reset once the summary was sent, so they are
+    /// carried over to the next summary if [`Level::Warn`] is disabled, the
+    /// summary is filtered out, or it fails to send.
     ///
     /// If given a pre-existing tokio runtime, it _will_ block the executor
     /// while it waits for the log to complete.
-    pub fn log_if<T: Debug>(
-        condition: fn() -> bool,
-        message: T,
-        surround: Option<u32>,
+    #[track_caller]
+    pub fn log_suppressed_summary(
         host: Option<&str>,
         port: Option<&str>,
         tokio_runtime: Option<&Handle>,
     ) -> LoggerResult<bool> {
-        if condition() {
-            Self::log(message, surround, host, port, tokio_runtime)?;
-            return Ok(true);
+        let Some((log, summary)) = Self::summary_log() else {
+            return Ok(false);
+        };
+
+        Self::send_log(log, host, port, tokio_runtime)?;
+        rate_limit::mark_reported(&summary);
+
+        Ok(true)
+    }
+
+    /// An async version of [`Self::log_suppressed_summary`], see
+    /// [`Self::log_async`].
+    #[track_caller]
+    pub fn log_suppressed_summary_async(
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<bool>> + Send + 'static {
+        let (log, summary) = Self::summary_log().unzip();
+        let send = Self::send_log_async(log, host, port, tokio_runtime);
+
+        async move {
+            send.await?;
+
+            match summary {
+                Some(summary) => {
+                    rate_limit::mark_reported(&summary);
+                    Ok(true)
+                },
+                None => Ok(false),
+            }
         }
+    }
 
-        Ok(false)
+    /// Shorthand for [`Self::log_at`] with [`Level::Trace`].
+    #[track_caller]
+    pub fn trace<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<()> {
+        Self::log_at(Level::Trace, message, surround, host, port, tokio_runtime)
+    }
+
+    /// Shorthand for [`Self::log_at_async`] with [`Level::Trace`].
+    #[track_caller]
+    pub fn trace_async<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<()>> + Send + 'static {
+        Self::log_at_async(Level::Trace, message, surround, host, port, tokio_runtime)
+    }
+
+    /// Shorthand for [`Self::log_at`] with [`Level::Debug`].
+    #[track_caller]
+    pub fn debug<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<()> {
+        Self::log_at(Level::Debug, message, surround, host, port, tokio_runtime)
+    }
+
+    /// Shorthand for [`Self::log_at_async`] with [`Level::Debug`].
+    #[track_caller]
+    pub fn debug_async<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<()>> + Send + 'static {
+        Self::log_at_async(Level::Debug, message, surround, host, port, tokio_runtime)
+    }
+
+    /// Shorthand for [`Self::log_at`] with [`Level::Info`].
+    #[track_caller]
+    pub fn info<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<()> {
+        Self::log_at(Level::Info, message, surround, host, port, tokio_runtime)
+    }
+
+    /// Shorthand for [`Self::log_at_async`] with [`Level::Info`].
+    #[track_caller]
+    pub fn info_async<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<()>> + Send + 'static {
+        Self::log_at_async(Level::Info, message, surround, host, port, tokio_runtime)
+    }
+
+    /// Shorthand for [`Self::log_at`] with [`Level::Warn`].
+    #[track_caller]
+    pub fn warn<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<()> {
+        Self::log_at(Level::Warn, message, surround, host, port, tokio_runtime)
+    }
+
+    /// Shorthand for [`Self::log_at_async`] with [`Level::Warn`].
+    #[track_caller]
+    pub fn warn_async<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<()>> + Send + 'static {
+        Self::log_at_async(Level::Warn, message, surround, host, port, tokio_runtime)
+    }
+
+    /// Shorthand for [`Self::log_at`] with [`Level::Error`].
+    #[track_caller]
+    pub fn error<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<()> {
+        Self::log_at(Level::Error, message, surround, host, port, tokio_runtime)
+    }
+
+    /// Shorthand for [`Self::log_at_async`] with [`Level::Error`].
+    #[track_caller]
+    pub fn error_async<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<()>> + Send + 'static {
+        Self::log_at_async(Level::Error, message, surround, host, port, tokio_runtime)
     }
 
-    /// A log function, similar to [`Self::log_if`] that takes a boxed closure
-    /// or function that can take in parameters from the outer scope.
+    /// A log function that takes a [`Condition`], such as a closure, and only
+    /// logs out if it is `true`. Essentially a conditional wrapper over
+    /// [`Self::log`]. See the [`condition`] module for built-in conditions
+    /// and how to combine them.
     ///
     /// If given a pre-existing tokio runtime, it _will_ block the executor
     /// while it waits for the log to complete.
-    pub fn boxed_log_if<T: Debug>(
-        condition: Box<dyn FnOnce() -> bool>,
+    #[track_caller]
+    pub fn log_if<T: Debug>(
+        condition: impl Condition,
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<bool> {
+        Self::log_if_at(
+            Level::Info,
+            condition,
+            message,
+            surround,
+            host,
+            port,
+            tokio_runtime,
+        )
+    }
+
+    /// A variation of [`Self::log_if`] that sends the log with the given
+    /// [`Level`]. The condition is not evaluated if `level` is below the
+    /// global minimum level.
+    #[track_caller]
+    pub fn log_if_at<T: Debug>(
+        level: Level,
+        condition: impl Condition,
         message: T,
         surround: Option<u32>,
         host: Option<&str>,
         port: Option<&str>,
         tokio_runtime: Option<&Handle>,
     ) -> LoggerResult<bool> {
-        if condition() {
-            Self::log(message, surround, host, port, tokio_runtime)?;
+        if admit(level.is_enabled() && condition.check()) {
+            Self::log_at(level, message, surround, host, port, tokio_runtime)?;
             return Ok(true);
         }
 
         Ok(false)
     }
 
-    /// A log function, similar to [`Self::log_if`] and [`Self::boxed_log_if`],
-    /// that only takes effect if the environment variable `CODECTRL_DEBUG`
-    /// is present or not.
+    /// An async version of [`Self::log_if`], see [`Self::log_async`].
+    #[track_caller]
+    pub fn log_if_async<T: Debug>(
+        condition: impl Condition,
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<bool>> + Send + 'static {
+        Self::log_if_at_async(
+            Level::Info,
+            condition,
+            message,
+            surround,
+            host,
+            port,
+            tokio_runtime,
+        )
+    }
+
+    /// An async version of [`Self::log_if_at`], see [`Self::log_async`].
+    #[track_caller]
+    pub fn log_if_at_async<T: Debug>(
+        level: Level,
+        condition: impl Condition,
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<bool>> + Send + 'static {
+        let passed = admit(level.is_enabled() && condition.check());
+        let log = match passed {
+            true => Self::new_log(level, || Message::debug(&message), surround),
+            false => None,
+        };
+        let send = Self::send_log_async(log, host, port, tokio_runtime);
+
+        async move { send.await.map(|()| passed) }
+    }
+
+    /// A log function, similar to [`Self::log_if`], that only takes effect if
+    /// the environment variable `CODECTRL_DEBUG` is present or not.
     ///
     /// If given a pre-existing tokio runtime, it _will_ block the executor
     /// while it waits for the log to complete.
+    #[track_caller]
     pub fn log_when_env<T: Debug>(
         message: T,
         surround: Option<u32>,
@@ -478,46 +2208,288 @@ impl<'a> Logger<'a> {
         port: Option<&str>,
         tokio_runtime: Option<&Handle>,
     ) -> LoggerResult<bool> {
-        if env::var("CODECTRL_DEBUG").ok().is_some() {
-            Self::log(message, surround, host, port, tokio_runtime)?;
-            Ok(true)
-        } else {
+        Self::log_when_env_at(Level::Info, message, surround, host, port, tokio_runtime)
+    }
+
+    /// A variation of [`Self::log_when_env`] that sends the log with the given
+    /// [`Level`].
+    #[track_caller]
+    pub fn log_when_env_at<T: Debug>(
+        level: Level,
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<bool> {
+        if !Self::env_admits(level) {
+            return Ok(false);
+        }
+
+        Self::log_at(level, message, surround, host, port, tokio_runtime)?;
+        Ok(true)
+    }
+
+    /// An async version of [`Self::log_when_env`], see [`Self::log_async`].
+    #[track_caller]
+    pub fn log_when_env_async<T: Debug>(
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<bool>> + Send + 'static {
+        Self::log_when_env_at_async(
+            Level::Info,
+            message,
+            surround,
+            host,
+            port,
+            tokio_runtime,
+        )
+    }
+
+    /// An async version of [`Self::log_when_env_at`], see [`Self::log_async`].
+    #[track_caller]
+    pub fn log_when_env_at_async<T: Debug>(
+        level: Level,
+        message: T,
+        surround: Option<u32>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<bool>> + Send + 'static {
+        let passed = Self::env_admits(level);
+        let log = match passed {
+            true => Self::new_log(level, || Message::debug(&message), surround),
+            false => None,
+        };
+        let send = Self::send_log_async(log, host, port, tokio_runtime);
+
+        async move { send.await.map(|()| passed) }
+    }
+
+    // Creates the log of the `log_X` functions with the global limits, unless
+    // `level` is below the minimum level.
+    #[track_caller]
+    fn new_log(
+        level: Level,
+        message: impl FnOnce() -> Message,
+        surround: Option<u32>,
+    ) -> Option<Log> {
+        if !admit(level.is_enabled()) {
+            return None;
+        }
+
+        create_log(level, message(), &limits(), surround, None, None)
+    }
+
+    // Creates the log of `log_limited_at`, if the `RateLimit` of the callsite
+    // allows it.
+    #[track_caller]
+    fn limited_log<T: Debug>(
+        level: Level,
+        limit: &RateLimit,
+        message: &T,
+        surround: Option<u32>,
+    ) -> Option<Log> {
+        if !admit(level.is_enabled()) {
+            return None;
+        }
+
+        let suppressed = rate_limit::check(limit, Location::caller())?;
+        let mut log = create_log(
+            level,
+            Message::debug(message),
+            &limits(),
+            surround,
+            None,
+            None,
+        )?;
+
+        if suppressed > 0 {
+            log.warnings
+                .push(Warning::Suppressed(suppressed).to_string());
+        }
+
+        Some(log)
+    }
+
+    // Creates the log of `log_suppressed_summary`, with the counts that it
+    // reports, unless there is nothing to report.
+    #[track_caller]
+    fn summary_log() -> Option<(Log, BTreeMap<rate_limit::Callsite, u64>)> {
+        if !Level::Warn.is_enabled() {
+            return None;
+        }
+
+        let summary = rate_limit::suppressed_summary();
+
+        if summary.is_empty() {
+            return None;
+        }
+
+        let log = create_log(
+            Level::Warn,
+            Message::debug(&rate_limit::summary_message(&summary)),
+            &limits(),
+            None,
+            None,
+            None,
+        )?;
+
+        Some((log, summary))
+    }
+
+    // Whether `CODECTRL_DEBUG` is present and `level` is enabled, for the
+    // `log_when_env` functions.
+    fn env_admits(level: Level) -> bool {
+        let env_present = env::var("CODECTRL_DEBUG").ok().is_some();
+
+        if !env_present && !cfg!(feature = "disabled") {
             #[cfg(debug_assertions)]
-            println!("log_when_env not called: envvar CODECTRL_DEBUG not present");
+            diagnostics::warn(
+                "envvar CODECTRL_DEBUG not present",
+                "log_when_env not called",
+            );
+        }
+
+        admit(env_present && level.is_enabled())
+    }
+
+    fn send_log(
+        log: Log,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<()> {
+        let endpoints =
+            Endpoints::registered(host.unwrap_or("127.0.0.1"), port.unwrap_or("3002"));
+
+        Self::send_log_to(log, endpoints, tokio_runtime)
+    }
+
+    // Without a runtime, the log is queued for the worker that keeps a
+    // connection to `endpoints`, and the reply is waited for at most for as long
+    // as connecting to each of them and sending may take. The log may still be
+    // sent after that.
+    #[cfg(not(feature = "disabled"))]
+    pub(crate) fn send_log_to(
+        log: Log,
+        endpoints: Endpoints,
+        tokio_runtime: Option<&Handle>,
+    ) -> LoggerResult<()> {
+        if tokio_runtime.is_some() {
+            return runtime::block_on(tokio_runtime, || Logger::_log(log, endpoints))?;
+        }
 
-            Ok(false)
+        let (sender, receiver) = std_mpsc::sync_channel(1);
+        let timeout = endpoints.send_timeout();
+
+        worker::send(
+            endpoints,
+            log,
+            Box::new(move |result| {
+                // The receiver is gone if waiting for the reply timed out.
+                let _ = sender.send(result);
+            }),
+        )?;
+
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(_) => Err(runtime::timed_out(timeout)),
         }
     }
 
-    // We have a non-async wrapper over _log so that we can log from non-async
-    // scopes.
-    //
-    // TODO: Provide a direct wrapper so that async environments do not need to call
-    // a non-async wrapper, just for that to call an async wrapper.
-    async fn _log(log: &mut Log, host: &str, port: &str) -> LoggerResult<()> {
-        let mut log_client =
-            LoggerClient::connect(format!("http://{host}:{port}")).await?;
-
-        let request = Request::new(log.clone());
-        let response = log_client.send_log(request).await?;
-
-        match response.into_inner() {
-            RequestResult { status, .. } if status == RequestStatus::Confirmed.into() =>
-                Ok(()),
-            RequestResult {
-                message,
-                status,
-                auth_status,
-            } if status == RequestStatus::Error.into() => Err(RequestResult {
-                message,
-                status,
-                auth_status,
+    #[cfg(feature = "disabled")]
+    pub(crate) fn send_log_to(
+        _: Log,
+        _: Endpoints,
+        _: Option<&Handle>,
+    ) -> LoggerResult<()> {
+        Ok(())
+    }
+
+    // Starts sending the log straight away, rather than when the future is
+    // first polled, so that it doesn't borrow the arguments. There is nothing to
+    // send if the log was filtered out.
+    #[cfg(not(feature = "disabled"))]
+    fn send_log_async(
+        log: Option<Log>,
+        host: Option<&str>,
+        port: Option<&str>,
+        tokio_runtime: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<()>> + Send + 'static {
+        let endpoints =
+            Endpoints::registered(host.unwrap_or("127.0.0.1"), port.unwrap_or("3002"));
+
+        let task = log.map(|log| match tokio_runtime {
+            Some(_) =>
+                runtime::spawn(tokio_runtime, move || Logger::_log(log, endpoints))
+                    .map(|task| task.boxed()),
+            None => {
+                let (sender, receiver) = oneshot::channel();
+
+                worker::send(
+                    endpoints,
+                    log,
+                    Box::new(move |result| {
+                        // The receiver is gone if the future was dropped.
+                        let _ = sender.send(result);
+                    }),
+                )
+                .map(|()| {
+                    receiver
+                        .map(|reply| reply.map_err(|_| worker_gone()))
+                        .boxed()
+                })
+            },
+        });
+
+        async move {
+            match task {
+                Some(task) => task?.await?,
+                None => Ok(()),
             }
-            .into()),
-            RequestResult { .. } => unreachable!(),
         }
     }
 
+    #[cfg(feature = "disabled")]
+    fn send_log_async(
+        _: Option<Log>,
+        _: Option<&str>,
+        _: Option<&str>,
+        _: Option<&Handle>,
+    ) -> impl Future<Output = LoggerResult<()>> + Send + 'static {
+        std::future::ready(Ok(()))
+    }
+
+    // Sends the log on the given tokio runtime, over a connection of its own.
+    // `send_log` blocks on this so that we can log from non-async scopes, and
+    // `send_log_async` spawns it so that any executor can await it.
+    #[cfg(not(feature = "disabled"))]
+    async fn _log(log: Log, endpoints: Endpoints) -> LoggerResult<()> {
+        let start = Instant::now();
+
+        fan_out::spawn_log(&endpoints, &log);
+
+        let result = async {
+            let mut log_client = transport::connect_any(&endpoints).await?;
+            let response = runtime::timeout(
+                SEND_TIMEOUT,
+                log_client.send_log(Request::new(log.clone())),
+            )
+            .await??;
+
+            check_result(response.into_inner())
+        }
+        .await;
+
+        stats::record_send(start, result.is_ok(), [&log]);
+
+        result
+    }
+
     fn get_stack_trace(log: &mut Log) {
         let backtrace = Backtrace::new();
 
@@ -543,23 +2515,24 @@ impl<'a> Logger<'a> {
                 {
                     let column_number = symbol.colno().unwrap_or_default();
 
-                    let mut file_path: String =
-                        if let Ok(path) = fs::canonicalize(file_name) {
-                            path.as_os_str().to_str().unwrap().to_string()
-                        } else {
-                            file_name.as_os_str().to_str().unwrap().to_string()
-                        };
+                    let file_path: String = if let Ok(path) = fs::canonicalize(file_name)
+                    {
+                        path.as_os_str().to_str().unwrap().to_string()
+                    } else {
+                        file_name.as_os_str().to_str().unwrap().to_string()
+                    };
 
                     #[cfg(target_os = "windows")]
-                    {
-                        file_path = file_path.replace("\\\\?\\", "");
-                    }
+                    let file_path = file_path.replace("\\\\?\\", "");
 
                     if !(name.contains("Logger::")
                         || name.contains("codectrl::Logger")
                         || name.contains("LogBatch::")
                         || name.contains("codectrl::LogBatch")
+                        || name.contains("AutoFlushBatch::")
+                        || name.contains("codectrl::timer::TimerGuard")
                         || name == "codectrl"
+                        || name.starts_with("codectrl::global")
                         || name.ends_with("create_log")
                         || name.contains("codectrl::create_log")
                         || file_path.contains(".cargo")
@@ -584,12 +2557,15 @@ impl<'a> Logger<'a> {
         }
     }
 
+    // The source file of a frame may not be there at runtime, i.e. when the
+    // binary is run away from where it was compiled, in which case the frame has
+    // no code, rather than failing the log.
     fn get_code(file_path: &str, line_number: u32) -> String {
         let mut code = String::new();
 
-        let file = File::open(file_path).unwrap_or_else(|_| {
-            panic!("Unexpected error: could not open file: {}", file_path)
-        });
+        let Ok(file) = File::open(file_path) else {
+            return code;
+        };
 
         let reader = BufReader::new(file);
 
@@ -608,9 +2584,9 @@ impl<'a> Logger<'a> {
         function_name: &str,
         function_name_occurences: Option<&HashBag<&'static str>>,
     ) -> BTreeMap<u32, String> {
-        let file = File::open(file_path).unwrap_or_else(|_| {
-            panic!("Unexpected error: could not open file: {}", file_path)
-        });
+        let Ok(file) = File::open(file_path) else {
+            return BTreeMap::new();
+        };
 
         let reader = BufReader::new(file);
 
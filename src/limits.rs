@@ -0,0 +1,168 @@
+use crate::Log;
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+// The smallest message that is worth keeping when it is truncated to fit into a
+// batch.
+pub(crate) const MIN_TRUNCATED_BYTES: usize = 256;
+
+/// Size limits applied to logs before they are sent, so that a single large
+/// message can't exceed the gRPC message limits of the server and cause the
+/// whole send to fail.
+///
+/// Set them globally with [`set_limits`], and per batch with
+/// [`LogBatch::limits`].
+///
+/// [`LogBatch::limits`]: crate::LogBatch::limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum size of a message in bytes. Larger messages keep their head
+    /// and tail and have the middle replaced by a marker. Defaults to 1 MiB.
+    pub max_message_bytes: Option<usize>,
+    /// The maximum size of all of the logs in a batch in bytes, counting their
+    /// messages, code snippets and stack traces. Once reached, messages are
+    /// truncated to fit into what is left and then further logs are dropped.
+    /// Defaults to 3 MiB.
+    pub max_batch_bytes: Option<usize>,
+    /// The maximum nesting depth of a [`Debug`] message, where the top-level
+    /// value is a depth of 0. Anything nested deeper is replaced by `...`.
+    /// Defaults to no limit.
+    ///
+    /// [`Debug`]: std::fmt::Debug
+    pub max_depth: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_message_bytes: Some(1024 * 1024),
+            max_batch_bytes: Some(3 * 1024 * 1024),
+            max_depth: None,
+        }
+    }
+}
+
+impl Limits {
+    /// Limits that do not restrict anything.
+    pub fn unlimited() -> Self {
+        Self {
+            max_message_bytes: None,
+            max_batch_bytes: None,
+            max_depth: None,
+        }
+    }
+}
+
+fn global() -> &'static RwLock<Limits> {
+    static LIMITS: OnceLock<RwLock<Limits>> = OnceLock::new();
+
+    LIMITS.get_or_init(Default::default)
+}
+
+/// Sets the global [`Limits`].
+pub fn set_limits(limits: Limits) {
+    *global().write().unwrap_or_else(PoisonError::into_inner) = limits;
+}
+
+/// Returns the global [`Limits`].
+pub fn limits() -> Limits { *global().read().unwrap_or_else(PoisonError::into_inner) }
+
+/// The approximate size of `log` when sent, counting its variable-length
+/// fields.
+pub(crate) fn log_size(log: &Log) -> usize {
+    let snippet = log.code_snippet.values().map(String::len).sum::<usize>();
+    let stack = log
+        .stack
+        .iter()
+        .map(|frame| frame.name.len() + frame.file_path.len() + frame.code.len())
+        .sum::<usize>();
+    let warnings = log.warnings.iter().map(String::len).sum::<usize>();
+
+    log.message.len()
+        + log.message_type.len()
+        + log.file_name.len()
+        + snippet
+        + stack
+        + warnings
+}
+
+/// Truncates `text` to at most `max_bytes` by keeping its head and tail,
+/// returning the original size if it was truncated. If not even the marker fits
+/// into `max_bytes`, only the head is kept.
+pub(crate) fn truncate(text: &mut String, max_bytes: usize) -> Option<usize> {
+    let original = text.len();
+
+    if original <= max_bytes {
+        return None;
+    }
+
+    let marker = format!("\n... [truncated from {original} bytes] ...\n");
+
+    if marker.len() > max_bytes {
+        text.truncate(floor_char_boundary(text, max_bytes));
+        return Some(original);
+    }
+
+    let keep = max_bytes.saturating_sub(marker.len());
+    let head = floor_char_boundary(text, keep / 2 + keep % 2);
+    let tail = ceil_char_boundary(text, original - keep / 2);
+
+    *text = format!("{}{marker}{}", &text[..head], &text[tail..]);
+
+    Some(original)
+}
+
+/// Replaces anything in pretty-printed [`Debug`] output that is nested deeper
+/// than `max_depth` with `...`, returning whether anything was replaced.
+///
+/// [`Debug`]: std::fmt::Debug
+pub(crate) fn limit_depth(text: &mut String, max_depth: usize) -> bool {
+    // `{:#?}` indents each level of nesting by 4 spaces.
+    let max_indent = (max_depth + 1) * 4;
+    let mut limited = String::with_capacity(text.len());
+    let mut skipping = false;
+
+    for line in text.lines() {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+
+        if indent >= max_indent {
+            if !skipping {
+                limited.push_str(&" ".repeat(max_indent));
+                limited.push_str("...\n");
+                skipping = true;
+            }
+
+            continue;
+        }
+
+        skipping = false;
+        limited.push_str(line);
+        limited.push('\n');
+    }
+
+    limited.pop();
+
+    // A message that was already limited has its `...` lines kept as they were.
+    if limited == *text {
+        return false;
+    }
+
+    *text = limited;
+
+    true
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+
+    index
+}
+
+fn ceil_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+
+    index
+}
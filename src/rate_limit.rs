@@ -0,0 +1,172 @@
+use crate::stats::{self, Counter};
+use std::{
+    collections::{BTreeMap, HashMap},
+    panic::Location,
+    sync::{Mutex, OnceLock, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// A per-callsite policy that limits how often a log is sent, used by
+/// [`Logger::log_limited`] and [`LogBatch::add_log_limited`].
+///
+/// The callsite is the file, line and column of the call to the logging
+/// function, so the same policy used in two different places is tracked
+/// separately.
+///
+/// [`Logger::log_limited`]: crate::Logger::log_limited
+/// [`LogBatch::add_log_limited`]: crate::LogBatch::add_log_limited
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimit {
+    /// Only the first log from the callsite is sent.
+    Once,
+    /// Only every nth log from the callsite is sent, starting with the first.
+    EveryNth(u64),
+    /// At most `n` logs from the callsite are sent per `Duration`.
+    AtMost(u32, Duration),
+    /// Each log from the callsite is sent with the given probability, between
+    /// `0.0` and `1.0`.
+    Sample(f64),
+}
+
+impl RateLimit {
+    /// See [`RateLimit::Once`].
+    pub fn once() -> Self { Self::Once }
+
+    /// See [`RateLimit::EveryNth`].
+    pub fn every_nth(n: u64) -> Self { Self::EveryNth(n) }
+
+    /// See [`RateLimit::AtMost`].
+    pub fn at_most(n: u32, per: Duration) -> Self { Self::AtMost(n, per) }
+
+    /// See [`RateLimit::Sample`].
+    pub fn sample(probability: f64) -> Self { Self::Sample(probability) }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct Callsite {
+    file: &'static str,
+    line: u32,
+    column: u32,
+}
+
+impl From<&'static Location<'static>> for Callsite {
+    fn from(location: &'static Location<'static>) -> Self {
+        Self {
+            file: location.file(),
+            line: location.line(),
+            column: location.column(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CallsiteState {
+    calls: u64,
+    window_start: Instant,
+    window_calls: u32,
+    // Suppressed since the last log that was sent from this callsite.
+    suppressed: u64,
+    // Suppressed since the last summary that was reported, see `mark_reported`.
+    unreported: u64,
+}
+
+impl CallsiteState {
+    fn new() -> Self {
+        Self {
+            calls: 0,
+            window_start: Instant::now(),
+            window_calls: 0,
+            suppressed: 0,
+            unreported: 0,
+        }
+    }
+
+    fn allows(&mut self, limit: &RateLimit) -> bool {
+        self.calls += 1;
+
+        match *limit {
+            RateLimit::Once => self.calls == 1,
+            RateLimit::EveryNth(n) => (self.calls - 1).is_multiple_of(n.max(1)),
+            RateLimit::AtMost(n, per) => {
+                if self.window_start.elapsed() >= per {
+                    self.window_start = Instant::now();
+                    self.window_calls = 0;
+                }
+
+                self.window_calls += 1;
+                self.window_calls <= n
+            },
+            RateLimit::Sample(probability) => rand::random::<f64>() < probability,
+        }
+    }
+}
+
+fn callsites() -> &'static Mutex<HashMap<Callsite, CallsiteState>> {
+    static CALLSITES: OnceLock<Mutex<HashMap<Callsite, CallsiteState>>> = OnceLock::new();
+
+    CALLSITES.get_or_init(Default::default)
+}
+
+/// Checks `limit` for the callsite at `location`. Returns `None` if the log
+/// should be suppressed, otherwise returns how many logs from this callsite
+/// were suppressed since the last one was sent.
+pub(crate) fn check(
+    limit: &RateLimit,
+    location: &'static Location<'static>,
+) -> Option<u64> {
+    let mut callsites = callsites().lock().unwrap_or_else(PoisonError::into_inner);
+    let state = callsites
+        .entry(location.into())
+        .or_insert_with(CallsiteState::new);
+
+    if state.allows(limit) {
+        Some(std::mem::take(&mut state.suppressed))
+    } else {
+        state.suppressed += 1;
+        state.unreported += 1;
+        stats::add(Counter::RateLimited, 1);
+
+        None
+    }
+}
+
+/// Returns the number of suppressed logs for each callsite since they were
+/// last reported. They are only reset by [`mark_reported`], once the summary
+/// was actually sent, so that they aren't lost if it isn't.
+pub(crate) fn suppressed_summary() -> BTreeMap<Callsite, u64> {
+    let callsites = callsites().lock().unwrap_or_else(PoisonError::into_inner);
+
+    callsites
+        .iter()
+        .filter(|(_, state)| state.unreported > 0)
+        .map(|(callsite, state)| (*callsite, state.unreported))
+        .collect()
+}
+
+/// The message of a summary returned by [`suppressed_summary`], with each
+/// callsite as `file:line:column`.
+pub(crate) fn summary_message(
+    summary: &BTreeMap<Callsite, u64>,
+) -> BTreeMap<String, u64> {
+    summary
+        .iter()
+        .map(|(callsite, count)| {
+            (
+                format!("{}:{}:{}", callsite.file, callsite.line, callsite.column),
+                *count,
+            )
+        })
+        .collect()
+}
+
+/// Resets the counts of a summary returned by [`suppressed_summary`]. Logs
+/// that were suppressed since it was taken are kept for the next one.
+pub(crate) fn mark_reported(summary: &BTreeMap<Callsite, u64>) {
+    let mut callsites = callsites().lock().unwrap_or_else(PoisonError::into_inner);
+
+    for (callsite, count) in summary {
+        if let Some(state) = callsites.get_mut(callsite) {
+            state.unreported = state.unreported.saturating_sub(*count);
+        }
+    }
+}
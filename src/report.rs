@@ -0,0 +1,139 @@
+use crate::{LoggerError, LoggerResult};
+use std::sync::Arc;
+
+/// How the logs of a batch are delivered to the server. Set with
+/// [`LogBatch::delivery_mode`].
+///
+/// [`LogBatch::delivery_mode`]: crate::LogBatch::delivery_mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeliveryMode {
+    /// All logs are sent in a single stream. The server only replies with one
+    /// result for the whole stream, so either every log is accepted or every
+    /// log fails.
+    #[default]
+    Stream,
+    /// Each log is sent with its own request over a single connection, so that
+    /// the result of every log is known. Slower than [`DeliveryMode::Stream`].
+    PerLog,
+}
+
+/// Which logs of a batch were accepted by the server and which failed, returned
+/// by [`Logger::send_batch_reported`] and [`LogBatch::send_reported`].
+///
+/// Logs are referred to by their index in the batch at the time it was sent.
+/// The logs that failed are kept in the batch, in their original order, so
+/// that they can be retried, or taken out with [`LogBatch::drain`].
+///
+/// If the batch fans out to other endpoints (see [`LogBatch::fan_out`]), this
+/// is the report of its `host`, and the report of each other endpoint is in
+/// [`Self::fan_out`].
+///
+/// [`Logger::send_batch_reported`]: crate::Logger::send_batch_reported
+/// [`LogBatch::send_reported`]: crate::LogBatch::send_reported
+/// [`LogBatch::drain`]: crate::LogBatch::drain
+/// [`LogBatch::fan_out`]: crate::LogBatch::fan_out
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub(crate) accepted: Vec<usize>,
+    pub(crate) failed: Vec<FailedLog>,
+    pub(crate) fan_out: Vec<EndpointReport>,
+}
+
+impl BatchReport {
+    /// The indices of the logs that were accepted by the server.
+    pub fn accepted(&self) -> &[usize] { &self.accepted }
+
+    /// The logs that failed to be delivered.
+    pub fn failed(&self) -> &[FailedLog] { &self.failed }
+
+    /// The reports of the other endpoints that the batch was sent to, in the
+    /// order that they were added.
+    pub fn fan_out(&self) -> &[EndpointReport] { &self.fan_out }
+
+    /// Whether every log was accepted, by every endpoint.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+            && self
+                .fan_out
+                .iter()
+                .all(|endpoint| endpoint.report.is_success())
+    }
+
+    /// Returns the error of the first log that failed, if any, checking the
+    /// `host` of the batch before the endpoints it fans out to.
+    pub fn into_result(mut self) -> LoggerResult<()> {
+        if self.failed.is_empty() {
+            return match self
+                .fan_out
+                .into_iter()
+                .find(|endpoint| !endpoint.report.is_success())
+            {
+                Some(endpoint) => endpoint.report.into_result(),
+                None => Ok(()),
+            };
+        }
+
+        let error = self.failed.swap_remove(0).error;
+
+        // With all-or-nothing delivery, every failed log shares the same error.
+        drop(self.failed);
+
+        Err(Arc::try_unwrap(error)
+            .unwrap_or_else(|error| LoggerError::LoggerError(error.to_string())))
+    }
+
+    #[cfg(not(feature = "disabled"))]
+    pub(crate) fn fail_all(&mut self, count: usize, error: LoggerError) {
+        let error = Arc::new(error);
+
+        self.failed.extend((0..count).map(|index| FailedLog {
+            index,
+            error: error.clone(),
+        }));
+    }
+}
+
+/// The result of sending a batch to one of the other endpoints that it fans
+/// out to, as part of a [`BatchReport`].
+///
+/// Every endpoint keeps the logs that it failed to receive and gets them again
+/// with the next batch, so its indices are of the logs that it was sent, which
+/// may include logs from earlier batches.
+#[derive(Debug)]
+pub struct EndpointReport {
+    pub(crate) host: String,
+    pub(crate) port: String,
+    pub(crate) report: BatchReport,
+    pub(crate) dropped: u64,
+}
+
+impl EndpointReport {
+    /// The host of the endpoint.
+    pub fn host(&self) -> &str { &self.host }
+
+    /// The port of the endpoint.
+    pub fn port(&self) -> &str { &self.port }
+
+    /// Which logs the endpoint accepted and which failed.
+    pub fn report(&self) -> &BatchReport { &self.report }
+
+    /// The number of logs of the batch that were never sent to the endpoint,
+    /// because its backlog was already at
+    /// [`max_batch_bytes`](crate::Limits::max_batch_bytes).
+    pub fn dropped(&self) -> u64 { self.dropped }
+}
+
+/// A log that failed to be delivered, as part of a [`BatchReport`].
+#[derive(Debug, Clone)]
+pub struct FailedLog {
+    pub(crate) index: usize,
+    pub(crate) error: Arc<LoggerError>,
+}
+
+impl FailedLog {
+    /// The index of the log in the batch at the time it was sent.
+    pub fn index(&self) -> usize { self.index }
+
+    /// Why the log failed to be delivered.
+    pub fn error(&self) -> &LoggerError { &self.error }
+}
@@ -0,0 +1,39 @@
+// The executor that logs are sent on. With the `rt-tokio` feature, which is the
+// default, that is a tokio runtime that is shared by the crate, or one that is
+// passed in as a `Handle`. With `rt-async-std`, it is the global executor of
+// async-std instead, and the connections to the server are made with its I/O
+// and timers, so that no tokio runtime is ever started.
+
+#[cfg(not(feature = "disabled"))]
+mod executor;
+
+#[cfg(not(feature = "disabled"))]
+pub(crate) use executor::*;
+
+#[cfg(feature = "disabled")]
+mod disabled;
+
+#[cfg(feature = "disabled")]
+pub(crate) use disabled::*;
+
+#[cfg(not(any(feature = "rt-tokio", feature = "rt-async-std", feature = "disabled")))]
+compile_error!(
+    "Either the `rt-tokio` or the `rt-async-std` feature must be enabled, unless \
+     logging is `disabled`"
+);
+
+/// A handle to a tokio runtime that logs can be sent on, rather than the
+/// executor that is shared by the crate, passed as the `tokio_runtime` of the
+/// logging functions.
+///
+/// The requests that are sent on it are bounded by its timers, so the runtime
+/// must be built with them enabled, e.g. with `enable_all`.
+#[cfg(feature = "rt-tokio")]
+pub use tokio::runtime::Handle;
+
+/// Stands in for a handle to a tokio runtime when the `rt-tokio` feature is
+/// disabled. It has no values, so `None` is the only `tokio_runtime` that can
+/// be passed to the logging functions.
+#[cfg(not(feature = "rt-tokio"))]
+#[derive(Debug, Clone)]
+pub enum Handle {}
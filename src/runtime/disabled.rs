@@ -0,0 +1,17 @@
+// Stands in for the executor when logging is `disabled`, for the code that is
+// shared with it but never gets as far as sending anything, since no log is
+// ever created.
+
+use crate::LoggerResult;
+use std::{future::Future, time::Duration};
+
+pub(crate) fn detach<F>(_: impl FnOnce() -> F) -> LoggerResult<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    Err(crate::disabled())
+}
+
+pub(crate) fn in_async_context() -> bool { false }
+
+pub(crate) async fn sleep(_: Duration) {}
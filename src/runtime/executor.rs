@@ -0,0 +1,301 @@
+use crate::{runtime::Handle, LoggerError, LoggerResult};
+use futures_util::{future::BoxFuture, FutureExt};
+use std::{future::Future, io, sync::mpsc, time::Duration};
+use tonic::transport::Endpoint;
+
+// The runtime that logs are sent on when no `Handle` is given. It is created
+// the first time that it is needed and lives until the program exits, so that
+// logging from synchronous code doesn't start and tear down a thread pool for
+// every log.
+#[cfg(not(feature = "rt-async-std"))]
+fn shared() -> LoggerResult<&'static tokio::runtime::Runtime> {
+    use std::sync::{Mutex, OnceLock, PoisonError};
+    use tokio::runtime::{Builder, Runtime};
+
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    static CREATING: Mutex<()> = Mutex::new(());
+
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+
+    // Only one runtime is ever created, as dropping a spare one would panic if
+    // it was created from inside of another runtime.
+    let _creating = CREATING.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+
+    let runtime = Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("codectrl-logger")
+        .enable_all()
+        .build()?;
+
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+// Runs the future made by `make_future` as a task on the shared executor, and
+// returns a future of its output. The future is only made once the executor
+// exists, so that nothing it takes ownership of is lost if the executor can't
+// be created.
+#[cfg(not(feature = "rt-async-std"))]
+fn spawn_shared<F>(
+    make_future: impl FnOnce() -> F,
+) -> LoggerResult<BoxFuture<'static, LoggerResult<F::Output>>>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let task = shared()?.spawn(make_future());
+
+    Ok(async move { task.await.map_err(|_| task_failed()) }.boxed())
+}
+
+#[cfg(feature = "rt-async-std")]
+fn spawn_shared<F>(
+    make_future: impl FnOnce() -> F,
+) -> LoggerResult<BoxFuture<'static, LoggerResult<F::Output>>>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let task = async_std::task::spawn(make_future());
+
+    Ok(async move { Ok(task.await) }.boxed())
+}
+
+// Runs the future made by `make_future` to completion on `handle`, blocking the
+// current thread, or on the shared executor if no handle is given. On the
+// shared executor the future runs as a task and the result is sent back over a
+// channel, which also works from inside of another runtime, where `block_on`
+// would panic. The futures that are run bound every connection and request
+// with `timeout`, so the wait is bounded too.
+pub(crate) fn block_on<F>(
+    handle: Option<&Handle>,
+    make_future: impl FnOnce() -> F,
+) -> LoggerResult<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    if let Some(handle) = handle {
+        return Ok(block_on_handle(handle, make_future()));
+    }
+
+    let (sender, receiver) = mpsc::sync_channel(1);
+
+    detach(|| {
+        let future = make_future();
+
+        async move {
+            // The receiver is only gone if the logging thread has panicked.
+            let _ = sender.send(future.await);
+        }
+    })?;
+
+    receiver.recv().map_err(|_| task_failed())
+}
+
+// Starts running the future made by `make_future` on `handle`, or on the shared
+// executor if no handle is given, and returns a future of its output. Unlike
+// the future that is spawned, which may need tokio for its I/O, the returned
+// future can be awaited on any executor.
+pub(crate) fn spawn<F>(
+    handle: Option<&Handle>,
+    make_future: impl FnOnce() -> F,
+) -> LoggerResult<BoxFuture<'static, LoggerResult<F::Output>>>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match handle {
+        Some(handle) => Ok(spawn_on_handle(handle, make_future())),
+        None => spawn_shared(make_future),
+    }
+}
+
+// The handle that the current future was given to run on, whose timers are
+// used by `timeout` instead of those of the shared runtime.
+#[cfg(feature = "rt-tokio")]
+tokio::task_local! {
+    static HANDLE: Handle;
+}
+
+#[cfg(feature = "rt-tokio")]
+fn block_on_handle<F: Future>(handle: &Handle, future: F) -> F::Output {
+    handle.block_on(HANDLE.scope(handle.clone(), future))
+}
+
+#[cfg(not(feature = "rt-tokio"))]
+fn block_on_handle<F: Future>(handle: &Handle, _: F) -> F::Output { match *handle {} }
+
+#[cfg(feature = "rt-tokio")]
+fn spawn_on_handle<F>(
+    handle: &Handle,
+    future: F,
+) -> BoxFuture<'static, LoggerResult<F::Output>>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let task = handle.spawn(HANDLE.scope(handle.clone(), future));
+
+    async move { task.await.map_err(|_| task_failed()) }.boxed()
+}
+
+#[cfg(not(feature = "rt-tokio"))]
+fn spawn_on_handle<F>(
+    handle: &Handle,
+    _: F,
+) -> BoxFuture<'static, LoggerResult<F::Output>>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match *handle {}
+}
+
+// Runs the future made by `make_future` on the shared executor, without
+// waiting for it.
+pub(crate) fn detach<F>(make_future: impl FnOnce() -> F) -> LoggerResult<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    #[cfg(not(feature = "rt-async-std"))]
+    shared()?.spawn(make_future());
+
+    #[cfg(feature = "rt-async-std")]
+    async_std::task::spawn(make_future());
+
+    Ok(())
+}
+
+// Whether the current thread is running an async executor, where blocking would
+// panic or hold up the other tasks.
+pub(crate) fn in_async_context() -> bool {
+    #[cfg(feature = "rt-tokio")]
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return true;
+    }
+
+    #[cfg(feature = "rt-async-std")]
+    if async_std::task::try_current().is_some() {
+        return true;
+    }
+
+    false
+}
+
+// Fails with an error if `future` doesn't complete within `duration`. With
+// tokio, `future` is polled in the context of the `Handle` that it was given to
+// run on, or else of the shared runtime, so that its timers, and those of the
+// connect timeout of an `endpoint`, are always those of a runtime of the crate
+// or of the user, and not of whichever runtime happens to poll it.
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> LoggerResult<F::Output> {
+    #[cfg(not(feature = "rt-async-std"))]
+    let timeout = {
+        let handle = match HANDLE.try_with(Handle::clone) {
+            Ok(handle) => handle,
+            Err(_) => shared()?.handle().clone(),
+        };
+        let _timers = handle.enter();
+        let mut timeout = Box::pin(tokio::time::timeout(duration, future));
+
+        std::future::poll_fn(move |cx| {
+            let _timers = handle.enter();
+            timeout.as_mut().poll(cx)
+        })
+    };
+
+    #[cfg(feature = "rt-async-std")]
+    let timeout = async_std::future::timeout(duration, future);
+
+    timeout.await.map_err(|_| timed_out(duration))
+}
+
+// Waits for `duration` on the timers of the executor, as with `timeout`.
+pub(crate) async fn sleep(duration: Duration) {
+    let _ = timeout(duration, std::future::pending::<()>()).await;
+}
+
+// An endpoint for `uri` that fails to connect after `connect_timeout`, and
+// whose background tasks are spawned on the shared executor, rather than with
+// `tokio::spawn`, if that isn't tokio. The connect timeout of tonic needs
+// tokio's timers, so with async-std it is only bounded by `timeout`.
+pub(crate) fn endpoint(uri: String, connect_timeout: Duration) -> LoggerResult<Endpoint> {
+    let endpoint = Endpoint::from_shared(uri)?;
+
+    #[cfg(not(feature = "rt-async-std"))]
+    let endpoint = endpoint.connect_timeout(connect_timeout);
+
+    #[cfg(feature = "rt-async-std")]
+    let endpoint = {
+        let _ = connect_timeout;
+        endpoint.executor(AsyncStdExecutor)
+    };
+
+    Ok(endpoint)
+}
+
+#[cfg(feature = "rt-async-std")]
+#[derive(Clone, Copy)]
+struct AsyncStdExecutor;
+
+#[cfg(feature = "rt-async-std")]
+impl<F> hyper::rt::Executor<F> for AsyncStdExecutor
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    fn execute(&self, future: F) { async_std::task::spawn(future); }
+}
+
+// Opens a TCP connection to `address` for an `endpoint`.
+#[cfg(not(feature = "rt-async-std"))]
+pub(crate) async fn connect_tcp(address: String) -> io::Result<tokio::net::TcpStream> {
+    let stream = tokio::net::TcpStream::connect(address).await?;
+    stream.set_nodelay(true)?;
+
+    Ok(stream)
+}
+
+#[cfg(feature = "rt-async-std")]
+pub(crate) async fn connect_tcp(
+    address: String,
+) -> io::Result<tokio_util::compat::Compat<async_std::net::TcpStream>> {
+    use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+    let stream = async_std::net::TcpStream::connect(address).await?;
+    stream.set_nodelay(true)?;
+
+    Ok(stream.compat())
+}
+
+// Opens a connection to the Unix domain socket at `path` for an `endpoint`.
+#[cfg(all(unix, not(feature = "rt-async-std")))]
+pub(crate) async fn connect_unix(path: String) -> io::Result<tokio::net::UnixStream> {
+    tokio::net::UnixStream::connect(path).await
+}
+
+#[cfg(all(unix, feature = "rt-async-std"))]
+pub(crate) async fn connect_unix(
+    path: String,
+) -> io::Result<tokio_util::compat::Compat<async_std::os::unix::net::UnixStream>> {
+    use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+    Ok(async_std::os::unix::net::UnixStream::connect(path)
+        .await?
+        .compat())
+}
+
+pub(crate) fn timed_out(duration: Duration) -> LoggerError {
+    LoggerError::LoggerError(format!("Timed out after {duration:?}"))
+}
+
+fn task_failed() -> LoggerError {
+    LoggerError::LoggerError("The task that was sending the log did not finish".into())
+}
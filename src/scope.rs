@@ -0,0 +1,134 @@
+use crate::Field;
+use std::{
+    cell::RefCell,
+    future::Future,
+    marker::PhantomData,
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// The key of the [`Field`] that carries the active scopes of a log, as a JSON
+/// array of their names from the outermost to the innermost, i.e.
+/// `"scopes":["handle_request","phase-2"]`.
+pub const SCOPES_FIELD: &str = "scopes";
+
+thread_local! {
+    static SCOPES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs `f` inside of a scope called `name`. Every log created on this thread
+/// while `f` runs carries the names of the active scopes in its
+/// [`SCOPES_FIELD`] field, giving it context that its stack trace can't show.
+///
+/// ```no_run
+/// use codectrl::Logger;
+///
+/// # fn main() -> Result<(), codectrl::LoggerError> {
+/// codectrl::scope("handle_request", || {
+///     // Sent with `"scopes":["handle_request"]`.
+///     Logger::log("Parsing headers", None, None, None, None)
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn scope<R>(name: impl Into<String>, f: impl FnOnce() -> R) -> R {
+    let _scope = enter(name);
+
+    f()
+}
+
+/// Enters a scope called `name` until the returned guard is dropped. See
+/// [`scope`].
+///
+/// The guard must be dropped on the thread that it was created on, so use
+/// [`in_scope`] for scopes that span an `.await` in a task that can move
+/// between threads.
+pub fn enter(name: impl Into<String>) -> ScopeGuard {
+    let depth = SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        scopes.push(name.into());
+        scopes.len() - 1
+    });
+
+    ScopeGuard {
+        depth,
+        _not_send: PhantomData,
+    }
+}
+
+/// Runs `future` inside of a scope called `name`, on top of the scopes that
+/// are active when this is called. The scopes follow the future across threads,
+/// so it can be spawned onto any executor.
+pub fn in_scope<F: Future>(name: impl Into<String>, future: F) -> InScope<F> {
+    let mut scopes = scopes();
+    scopes.push(name.into());
+
+    InScope {
+        scopes,
+        future: Box::pin(future),
+    }
+}
+
+/// The names of the scopes that are active on this thread, from the outermost
+/// to the innermost.
+pub fn scopes() -> Vec<String> { SCOPES.with(|scopes| scopes.borrow().clone()) }
+
+// The field carrying the active scopes, if there are any.
+pub(crate) fn field() -> Option<Field> {
+    SCOPES.with(|scopes| {
+        let scopes = scopes.borrow();
+
+        (!scopes.is_empty()).then(|| Field::serialized(SCOPES_FIELD, &*scopes))
+    })
+}
+
+/// Leaves the scope entered with [`enter`], and any scopes entered after it,
+/// when dropped.
+#[must_use = "the scope is left as soon as the guard is dropped"]
+pub struct ScopeGuard {
+    depth: usize,
+    // The scope is stored in a thread-local, so it must be left on the same
+    // thread.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) { SCOPES.with(|scopes| scopes.borrow_mut().truncate(self.depth)); }
+}
+
+/// A future that runs inside of a scope, created with [`in_scope`].
+pub struct InScope<F> {
+    scopes: Vec<String>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for InScope<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let _scopes = SwapScopes::new(&mut this.scopes);
+
+        this.future.as_mut().poll(cx)
+    }
+}
+
+// Swaps the scopes of this thread with those of a future while it is being
+// polled, and back again afterwards, even if polling panics. Any scope that is
+// entered by the future and still active is kept for the next poll.
+struct SwapScopes<'a>(&'a mut Vec<String>);
+
+impl<'a> SwapScopes<'a> {
+    fn new(scopes: &'a mut Vec<String>) -> Self {
+        SCOPES.with(|current| mem::swap(&mut *current.borrow_mut(), scopes));
+
+        Self(scopes)
+    }
+}
+
+impl Drop for SwapScopes<'_> {
+    fn drop(&mut self) {
+        SCOPES.with(|current| mem::swap(&mut *current.borrow_mut(), self.0));
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+#[cfg(not(feature = "disabled"))]
+use crate::{limits, Log};
+#[cfg(not(feature = "disabled"))]
+use std::time::Instant;
+
+// The upper bounds of the buckets of the send latency histogram.
+const LATENCY_BOUNDS: [Duration; 12] = [
+    Duration::from_millis(1),
+    Duration::from_millis(2),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+];
+
+/// A snapshot of what the logger has done since the program started, across
+/// every [`Logger`](crate::Logger), [`LogBatch`](crate::LogBatch) and the
+/// global logger. Returned by [`Logger::stats`](crate::Logger::stats).
+///
+/// Logs that are sent to several endpoints, see
+/// [`LogBatch::fan_out`](crate::LogBatch::fan_out), are counted once for each
+/// endpoint when they are sent, fail, or are retried.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Logs that were created, after they passed the level and filters.
+    pub logs_created: u64,
+    /// Logs that were accepted by the server.
+    pub logs_sent: u64,
+    /// Logs that failed to be delivered, including the ones that are kept to
+    /// be retried.
+    pub logs_failed: u64,
+    /// Logs that were not created because they were below the minimum level,
+    /// their condition was false, `CODECTRL_DEBUG` was not present for
+    /// [`Logger::log_when_env`](crate::Logger::log_when_env), or they were
+    /// dropped by the [`Filter`](crate::Filter).
+    pub logs_filtered: u64,
+    /// Logs that were suppressed by a [`RateLimit`](crate::RateLimit).
+    pub logs_rate_limited: u64,
+    /// Logs that were dropped because a batch reached its
+    /// [`max_batch_bytes`](crate::Limits::max_batch_bytes).
+    pub logs_dropped: u64,
+    /// Logs that were sent again after they failed before, once for every time
+    /// that they were sent again.
+    pub logs_retried: u64,
+    /// Logs that were kept in a batch after they failed, to be retried with the
+    /// next send. Each log is only counted the first time that it failed.
+    pub logs_spooled: u64,
+    /// The size of the logs that were accepted by the server, as counted for
+    /// [`max_batch_bytes`](crate::Limits::max_batch_bytes).
+    pub bytes_sent: u64,
+    /// How long each request to the server took, including connecting to it.
+    pub send_latency: LatencyHistogram,
+}
+
+/// A histogram of how long requests to the server took, as part of [`Stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+}
+
+impl LatencyHistogram {
+    /// The buckets of the histogram, as the upper bound of each bucket and the
+    /// number of requests that took longer than the bound before it, but no
+    /// longer than its own. The last bucket has no upper bound.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        LATENCY_BOUNDS
+            .iter()
+            .copied()
+            .map(Some)
+            .chain([None])
+            .zip(self.counts.iter().copied())
+    }
+
+    /// The number of requests in the histogram.
+    pub fn count(&self) -> u64 { self.counts.iter().sum() }
+}
+
+pub(crate) enum Counter {
+    Created,
+    Sent,
+    Failed,
+    Filtered,
+    RateLimited,
+    Dropped,
+    Retried,
+    Spooled,
+    BytesSent,
+}
+
+struct Counters {
+    values: [AtomicU64; 9],
+    latency: [AtomicU64; LATENCY_BOUNDS.len() + 1],
+}
+
+static COUNTERS: Counters = Counters {
+    values: [const { AtomicU64::new(0) }; 9],
+    latency: [const { AtomicU64::new(0) }; LATENCY_BOUNDS.len() + 1],
+};
+
+pub(crate) fn add(counter: Counter, count: u64) {
+    if count > 0 {
+        COUNTERS.values[counter as usize].fetch_add(count, Ordering::Relaxed);
+    }
+}
+
+// Records a request to the server that started at `start` and sent `logs`,
+// which were either all accepted or all failed.
+#[cfg(not(feature = "disabled"))]
+pub(crate) fn record_send<'a>(
+    start: Instant,
+    accepted: bool,
+    logs: impl IntoIterator<Item = &'a Log>,
+) {
+    let latency = start.elapsed();
+    let bucket = LATENCY_BOUNDS
+        .iter()
+        .position(|bound| latency <= *bound)
+        .unwrap_or(LATENCY_BOUNDS.len());
+
+    COUNTERS.latency[bucket].fetch_add(1, Ordering::Relaxed);
+
+    let (count, bytes) = logs.into_iter().fold((0, 0), |(count, bytes), log| {
+        (count + 1, bytes + limits::log_size(log))
+    });
+
+    if accepted {
+        add(Counter::Sent, count);
+        add(Counter::BytesSent, bytes as u64);
+    } else {
+        add(Counter::Failed, count);
+    }
+}
+
+pub(crate) fn snapshot() -> Stats {
+    let value =
+        |counter: Counter| COUNTERS.values[counter as usize].load(Ordering::Relaxed);
+
+    Stats {
+        logs_created: value(Counter::Created),
+        logs_sent: value(Counter::Sent),
+        logs_failed: value(Counter::Failed),
+        logs_filtered: value(Counter::Filtered),
+        logs_rate_limited: value(Counter::RateLimited),
+        logs_dropped: value(Counter::Dropped),
+        logs_retried: value(Counter::Retried),
+        logs_spooled: value(Counter::Spooled),
+        bytes_sent: value(Counter::BytesSent),
+        send_latency: LatencyHistogram {
+            counts: COUNTERS
+                .latency
+                .iter()
+                .map(|count| count.load(Ordering::Relaxed))
+                .collect(),
+        },
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{admit, diagnostics, global, Field, Level, Message};
+use std::{
+    panic::Location,
+    time::{Duration, Instant},
+};
+
+/// Sends a log with the time that has elapsed since it was created when it is
+/// dropped, pointing at the line where it was created. Created with
+/// [`Logger::timer`].
+///
+/// The log is sent with the global logger, see [`init`](crate::init). Its
+/// message is `"{label} took {elapsed:?}"` and the elapsed time in
+/// milliseconds is attached as the `elapsed_ms` [`Field`].
+///
+/// ```no_run
+/// use codectrl::Logger;
+/// use std::time::Duration;
+///
+/// fn handle_request() {
+///     let _timer = Logger::timer("handle_request").threshold(Duration::from_millis(50));
+///
+///     // Only sent if the rest of the function takes 50ms or more.
+/// }
+/// ```
+#[must_use = "the time is sent as soon as the guard is dropped"]
+pub struct TimerGuard {
+    label: String,
+    start: Instant,
+    threshold: Option<Duration>,
+    level: Level,
+    location: &'static Location<'static>,
+}
+
+impl TimerGuard {
+    pub(crate) fn new(
+        label: impl Into<String>,
+        location: &'static Location<'static>,
+    ) -> Self {
+        Self {
+            label: label.into(),
+            start: Instant::now(),
+            threshold: None,
+            level: Level::Info,
+            location,
+        }
+    }
+
+    /// Only sends the log if at least `threshold` has elapsed.
+    pub fn threshold(mut self, threshold: Duration) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    /// Sets the [`Level`] of the log. Defaults to [`Level::Info`].
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// The time that has elapsed since the guard was created.
+    pub fn elapsed(&self) -> Duration { self.start.elapsed() }
+}
+
+impl Drop for TimerGuard {
+    fn drop(&mut self) {
+        let elapsed = self.elapsed();
+
+        if self.threshold.is_some_and(|threshold| elapsed < threshold)
+            || !admit(self.level.is_enabled())
+        {
+            return;
+        }
+
+        let message = Message::debug(&format!("{} took {elapsed:?}", self.label))
+            .with_fields([Field::serialized(
+                "elapsed_ms",
+                elapsed.as_secs_f64() * 1000.0,
+            )])
+            .at(self.location);
+
+        let ret = global::send(self.level, message);
+
+        // There is no way to report an error from `drop`.
+        if let Err(e) = ret {
+            diagnostics::warn(
+                "Could not send the time taken",
+                format_args!("{}: {e}", self.label),
+            );
+        }
+    }
+}
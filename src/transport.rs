@@ -0,0 +1,414 @@
+use crate::{LoggerError, LoggerResult};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    sync::{OnceLock, PoisonError, RwLock},
+    time::Duration,
+};
+
+#[cfg(not(feature = "disabled"))]
+use crate::runtime;
+#[cfg(not(feature = "disabled"))]
+use codectrl_protobuf_bindings::logs_service::LoggerClient;
+#[cfg(not(feature = "disabled"))]
+use std::{sync::Mutex, time::Instant};
+#[cfg(not(feature = "disabled"))]
+use tonic::transport::{Channel, Uri};
+#[cfg(not(feature = "disabled"))]
+use tower::service_fn;
+
+/// The prefix of a host that is the path of a Unix domain socket rather than a
+/// network address, i.e. `unix:///tmp/codectrl.sock`. The port is ignored for
+/// such a host.
+pub const UNIX_SOCKET_PREFIX: &str = "unix://";
+
+/// How long the endpoint that was failed over to keeps being used before the
+/// first endpoint is tried again, unless it is set with
+/// [`LogBatch::failover_cooldown`](crate::LogBatch::failover_cooldown) or
+/// [`Config::failover_cooldown`](crate::Config::failover_cooldown).
+pub const DEFAULT_FAILOVER_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// How long connecting to each endpoint may take before the next one is tried,
+/// unless it is set with
+/// [`LogBatch::connect_timeout`](crate::LogBatch::connect_timeout) or
+/// [`Config::connect_timeout`](crate::Config::connect_timeout).
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a request to the server may take before it fails. A log that is
+/// sent from synchronous code waits at most this long for the server to reply,
+/// plus the connect timeout of each endpoint that may have to be tried.
+pub const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The endpoints that logs are sent to, as host and port pairs. Only the first
+// is used, unless it can't be connected to, in which case the next one is
+// tried, and so on. Single logs are also sent to every endpoint in `fan_out`,
+// see `fan_out::send_log`, while batches keep their own.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Endpoints {
+    pub(crate) endpoints: Vec<(String, String)>,
+    pub(crate) cooldown: Duration,
+    pub(crate) connect_timeout: Duration,
+    pub(crate) fan_out: Vec<(String, String)>,
+}
+
+impl Endpoints {
+    pub(crate) fn new(host: &str, port: &str) -> Self {
+        Self {
+            endpoints: vec![(host.to_string(), port.to_string())],
+            cooldown: DEFAULT_FAILOVER_COOLDOWN,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            fan_out: Vec::new(),
+        }
+    }
+
+    // The endpoints for `host` and `port`, with the servers that were set to
+    // fail over to and fan out to for them with `set_failover` and
+    // `set_fan_out`, if any.
+    pub(crate) fn registered(host: &str, port: &str) -> Self {
+        let endpoints = registry()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(host.to_string(), port.to_string()))
+            .cloned();
+
+        endpoints.unwrap_or_else(|| Self::new(host, port))
+    }
+
+    pub(crate) fn failover<'a>(
+        mut self,
+        endpoints: impl IntoIterator<Item = (&'a str, &'a str)>,
+        cooldown: Duration,
+    ) -> Self {
+        self.endpoints.extend(
+            endpoints
+                .into_iter()
+                .map(|(host, port)| (host.to_string(), port.to_string())),
+        );
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub(crate) fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub(crate) fn fan_out<'a>(
+        mut self,
+        endpoints: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Self {
+        self.fan_out = endpoints
+            .into_iter()
+            .map(|(host, port)| (host.to_string(), port.to_string()))
+            .collect();
+        self
+    }
+
+    // How long sending a log may take at most, if every endpoint has to be
+    // tried.
+    #[cfg(not(feature = "disabled"))]
+    pub(crate) fn send_timeout(&self) -> Duration {
+        self.connect_timeout * self.endpoints.len() as u32 + SEND_TIMEOUT
+    }
+}
+
+// The endpoints that were set with `set_failover` and `set_fan_out`, by host
+// and port.
+type Registry = HashMap<(String, String), Endpoints>;
+
+fn registry() -> &'static RwLock<Registry> {
+    static REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
+
+    REGISTRY.get_or_init(Default::default)
+}
+
+// Updates the endpoints that are registered for `host` and `port`, which are
+// removed once there is nothing to fail over or fan out to.
+fn register(host: &str, port: &str, update: impl FnOnce(Endpoints) -> Endpoints) {
+    let key = (host.to_string(), port.to_string());
+    let mut registry = registry().write().unwrap_or_else(PoisonError::into_inner);
+    let endpoints = update(
+        registry
+            .remove(&key)
+            .unwrap_or_else(|| Endpoints::new(host, port)),
+    );
+
+    if endpoints.endpoints.len() > 1 || !endpoints.fan_out.is_empty() {
+        registry.insert(key, endpoints);
+    }
+}
+
+/// Sets the servers to fail over to, in order, when the server at `host` and
+/// `port` can't be connected to by the [`Logger`](crate::Logger) functions
+/// that are given that host and port, i.e. [`Logger::log`](crate::Logger::log).
+/// Replaces the servers that were set for them before, and passing no servers
+/// removes them. Batches and the global logger have their own, see
+/// [`LogBatch::failover`](crate::LogBatch::failover) and
+/// [`Config::failover`](crate::Config::failover).
+///
+/// ```no_run
+/// use codectrl::{Logger, DEFAULT_FAILOVER_COOLDOWN};
+///
+/// # fn main() -> Result<(), codectrl::LoggerError> {
+/// codectrl::set_failover(
+///     "10.0.0.4",
+///     "3002",
+///     [("10.0.0.5", "3002")],
+///     DEFAULT_FAILOVER_COOLDOWN,
+/// );
+///
+/// // Sent to 10.0.0.5 if 10.0.0.4 can't be connected to.
+/// Logger::log("Hello", None, Some("10.0.0.4"), Some("3002"), None)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn set_failover<'a>(
+    host: &str,
+    port: &str,
+    failover: impl IntoIterator<Item = (&'a str, &'a str)>,
+    cooldown: Duration,
+) {
+    register(host, port, |mut endpoints| {
+        endpoints.endpoints.truncate(1);
+        endpoints.failover(failover, cooldown)
+    });
+}
+
+/// Sets the servers that the [`Logger`](crate::Logger) functions also send
+/// every log to when they are given `host` and `port`, i.e.
+/// [`Logger::log`](crate::Logger::log). Replaces the servers that were set for
+/// them before, and passing no servers removes them. Batches and the global
+/// logger have their own, see [`LogBatch::fan_out`](crate::LogBatch::fan_out)
+/// and [`Config::fan_out`](crate::Config::fan_out).
+///
+/// Each server keeps the logs that it failed to receive, up to
+/// [`max_batch_bytes`](crate::Limits::max_batch_bytes), and is sent them again
+/// with the next log. Only the result of `host` is returned.
+///
+/// ```no_run
+/// use codectrl::Logger;
+///
+/// # fn main() -> Result<(), codectrl::LoggerError> {
+/// codectrl::set_fan_out("127.0.0.1", "3002", [("10.0.0.5", "3002")]);
+///
+/// Logger::log("Sent to 127.0.0.1 and 10.0.0.5", None, None, None, None)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn set_fan_out<'a>(
+    host: &str,
+    port: &str,
+    fan_out: impl IntoIterator<Item = (&'a str, &'a str)>,
+) {
+    register(host, port, |endpoints| endpoints.fan_out(fan_out));
+}
+
+// The index of the endpoint that was failed over to for each list of
+// endpoints, and when it was connected to.
+#[cfg(not(feature = "disabled"))]
+type Healthy = HashMap<Vec<(String, String)>, (usize, Instant)>;
+
+#[cfg(not(feature = "disabled"))]
+fn healthy() -> &'static Mutex<Healthy> {
+    static HEALTHY: OnceLock<Mutex<Healthy>> = OnceLock::new();
+
+    HEALTHY.get_or_init(Default::default)
+}
+
+// Connects to the first of `endpoints` that can be connected to, starting with
+// the one that was failed over to last, if that was within the cool-down.
+// Returns the error of the first endpoint that was tried if none can be.
+#[cfg(not(feature = "disabled"))]
+pub(crate) async fn connect_any(
+    endpoints: &Endpoints,
+) -> LoggerResult<LoggerClient<Channel>> {
+    connect_first(endpoints).await.map(|(_, client)| client)
+}
+
+// `connect_any`, which also returns the index of the endpoint that was
+// connected to.
+#[cfg(not(feature = "disabled"))]
+pub(crate) async fn connect_first(
+    endpoints: &Endpoints,
+) -> LoggerResult<(usize, LoggerClient<Channel>)> {
+    let list = &endpoints.endpoints;
+
+    let timeout = endpoints.connect_timeout;
+
+    if let [(host, port)] = list.as_slice() {
+        return Ok((0, connect(host, port, timeout).await?));
+    }
+
+    let start = healthy()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(list)
+        .filter(|(_, since)| since.elapsed() < endpoints.cooldown)
+        .map_or(0, |(index, _)| *index);
+
+    let order = [start]
+        .into_iter()
+        .chain((0..list.len()).filter(|&index| index != start));
+
+    let mut first_error = None;
+
+    for index in order {
+        let (host, port) = &list[index];
+
+        match connect(host, port, timeout).await {
+            Ok(client) => {
+                let mut healthy =
+                    healthy().lock().unwrap_or_else(PoisonError::into_inner);
+
+                match index {
+                    0 => healthy.remove(list),
+                    // Only the time of the failover is remembered, so that the
+                    // first endpoint is tried again once the cool-down is over.
+                    _ if index == start => None,
+                    _ => healthy.insert(list.clone(), (index, Instant::now())),
+                };
+
+                return Ok((index, client));
+            },
+            Err(e) => {
+                first_error.get_or_insert(e);
+            },
+        }
+    }
+
+    Err(first_error.expect("there is at least one endpoint"))
+}
+
+/// How long [`Logger::check_connection`](crate::Logger::check_connection)
+/// waits for the server to accept the connection before it gives up.
+pub const CONNECTION_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether the server could be connected to, returned by
+/// [`Logger::check_connection`](crate::Logger::check_connection).
+///
+/// Its [`Display`] output is a message that can be shown to the user, i.e.
+/// `CodeCTRL not reachable at 127.0.0.1:3002: ...`.
+#[derive(Debug)]
+pub struct ConnectionCheck {
+    host: String,
+    port: String,
+    latency: Duration,
+    error: Option<LoggerError>,
+}
+
+impl ConnectionCheck {
+    pub(crate) fn failed(host: String, port: String, error: LoggerError) -> Self {
+        Self {
+            host,
+            port,
+            latency: Duration::ZERO,
+            error: Some(error),
+        }
+    }
+
+    /// The host that was checked.
+    pub fn host(&self) -> &str { &self.host }
+
+    /// The port that was checked.
+    pub fn port(&self) -> &str { &self.port }
+
+    /// Whether the server accepted the connection.
+    pub fn is_reachable(&self) -> bool { self.error.is_none() }
+
+    /// How long it took for the connection to be established, or to fail.
+    pub fn latency(&self) -> Duration { self.latency }
+
+    /// Why the server could not be connected to, if it couldn't be.
+    pub fn error(&self) -> Option<&LoggerError> { self.error.as_ref() }
+
+    /// Returns the latency if the server was reachable, or else the error.
+    pub fn into_result(self) -> LoggerResult<Duration> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.latency),
+        }
+    }
+}
+
+impl Display for ConnectionCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let address = match self.host.starts_with(UNIX_SOCKET_PREFIX) {
+            true => self.host.clone(),
+            false => format!("{}:{}", self.host, self.port),
+        };
+
+        match &self.error {
+            Some(error) => write!(f, "CodeCTRL not reachable at {address}: {error}"),
+            None => write!(f, "CodeCTRL reachable at {address} in {:?}", self.latency),
+        }
+    }
+}
+
+// Establishes a connection to the server without sending anything over it.
+#[cfg(not(feature = "disabled"))]
+pub(crate) async fn check(host: String, port: String) -> ConnectionCheck {
+    let start = Instant::now();
+
+    let error = connect(&host, &port, CONNECTION_CHECK_TIMEOUT).await.err();
+
+    ConnectionCheck {
+        host,
+        port,
+        latency: start.elapsed(),
+        error,
+    }
+}
+
+// Connects to the server at `host` and `port`, or to the Unix domain socket
+// that `host` points to, failing if it takes longer than `timeout`. The timeout
+// is set on the endpoint, so that it bounds opening the socket, and around it,
+// so that it also bounds the HTTP/2 handshake.
+#[cfg(not(feature = "disabled"))]
+async fn connect(
+    host: &str,
+    port: &str,
+    timeout: Duration,
+) -> LoggerResult<LoggerClient<Channel>> {
+    runtime::timeout(timeout, connect_once(host, port, timeout)).await?
+}
+
+#[cfg(not(feature = "disabled"))]
+async fn connect_once(
+    host: &str,
+    port: &str,
+    timeout: Duration,
+) -> LoggerResult<LoggerClient<Channel>> {
+    let channel = match host.strip_prefix(UNIX_SOCKET_PREFIX) {
+        Some(path) => connect_unix(path.to_string(), timeout).await?,
+        None => {
+            let address = format!("{host}:{port}");
+
+            runtime::endpoint(format!("http://{address}"), timeout)?
+                .connect_with_connector(service_fn(move |_: Uri| {
+                    runtime::connect_tcp(address.clone())
+                }))
+                .await?
+        },
+    };
+
+    Ok(LoggerClient::new(channel))
+}
+
+#[cfg(all(unix, not(feature = "disabled")))]
+async fn connect_unix(path: String, timeout: Duration) -> LoggerResult<Channel> {
+    // Tonic needs a URI for the endpoint, but it isn't used by the connector.
+    let channel = runtime::endpoint("http://localhost".into(), timeout)?
+        .connect_with_connector(service_fn(move |_: Uri| {
+            runtime::connect_unix(path.clone())
+        }))
+        .await?;
+
+    Ok(channel)
+}
+
+#[cfg(all(not(unix), not(feature = "disabled")))]
+async fn connect_unix(path: String, _: Duration) -> LoggerResult<Channel> {
+    Err(crate::LoggerError::LoggerError(format!(
+        "Can't connect to {path}: Unix domain sockets are not supported on this platform"
+    )))
+}
@@ -0,0 +1,159 @@
+use crate::{
+    check_result, fan_out, runtime, stats,
+    transport::{self, Endpoints, SEND_TIMEOUT},
+    LoggerError, LoggerResult,
+};
+use codectrl_protobuf_bindings::{data::Log, logs_service::LoggerClient};
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock, PoisonError},
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tonic::{transport::Channel, Request};
+
+// How many logs can be waiting for a worker before more are refused, rather
+// than queueing without bound while the server doesn't reply.
+const QUEUE_CAPACITY: usize = 1024;
+
+// How long a worker waits for another log before it stops, so that there is no
+// worker left behind for every list of endpoints that was ever logged to.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Called with the result of sending a log, once the worker has sent it.
+pub(crate) type Reply = Box<dyn FnOnce(LoggerResult<()>) + Send>;
+
+struct Job {
+    log: Log,
+    reply: Reply,
+}
+
+// The queue of the worker for each list of endpoints and their settings.
+type Workers = HashMap<Endpoints, mpsc::Sender<Job>>;
+
+fn workers() -> &'static Mutex<Workers> {
+    static WORKERS: OnceLock<Mutex<Workers>> = OnceLock::new();
+
+    WORKERS.get_or_init(Default::default)
+}
+
+// Queues `log` to be sent to `endpoints` by the worker for them, which is
+// started on the shared executor when there is none. `reply` is
+// called with the result once the log was sent, unless it couldn't be queued,
+// in which case the error is returned instead.
+pub(crate) fn send(endpoints: Endpoints, log: Log, reply: Reply) -> LoggerResult<()> {
+    let mut workers = workers().lock().unwrap_or_else(PoisonError::into_inner);
+    let job = Job { log, reply };
+
+    let job = match workers.get(&endpoints) {
+        Some(queue) => match queue.try_send(job) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Full(_)) => {
+                return Err(LoggerError::LoggerError(format!(
+                    "Can't send log: {QUEUE_CAPACITY} logs are already waiting to be \
+                     sent"
+                )));
+            },
+            // The worker is only gone if it panicked, so a new one is started.
+            Err(TrySendError::Closed(job)) => job,
+        },
+        None => job,
+    };
+
+    let (queue, receiver) = mpsc::channel(QUEUE_CAPACITY);
+    let key = endpoints.clone();
+
+    runtime::detach(|| run(endpoints, receiver))?;
+
+    // The queue is empty, so there is room for the job.
+    let _ = queue.try_send(job);
+    workers.insert(key, queue);
+
+    Ok(())
+}
+
+// A client and whether it was connected to an endpoint that was failed over
+// to, and when.
+struct Connection {
+    client: LoggerClient<Channel>,
+    failed_over: Option<Instant>,
+}
+
+// Sends the logs from `queue` one after the other, keeping the connection
+// between them, and fans each of them out. It reconnects after an error, or
+// once the cool-down of an endpoint that was failed over to is over, so that
+// the first endpoint is tried again.
+async fn run(endpoints: Endpoints, mut queue: mpsc::Receiver<Job>) {
+    let mut connection = None;
+
+    while let Some(Job { log, reply }) = next_job(&endpoints, &mut queue).await {
+        let start = Instant::now();
+
+        fan_out::spawn_log(&endpoints, &log);
+
+        let result = send_log(&endpoints, &mut connection, &log).await;
+
+        if result.is_err() {
+            connection = None;
+        }
+
+        stats::record_send(start, result.is_ok(), [&log]);
+        reply(result);
+    }
+}
+
+// Waits for the next job in `queue`. If there is none within `IDLE_TIMEOUT`,
+// the queue is closed and removed from the workers, and `None` is returned.
+async fn next_job(endpoints: &Endpoints, queue: &mut mpsc::Receiver<Job>) -> Option<Job> {
+    if let Ok(job) = runtime::timeout(IDLE_TIMEOUT, queue.recv()).await {
+        return job;
+    }
+
+    // Jobs are only queued while the workers are locked, so none can be queued
+    // between checking for one and closing the queue.
+    let mut workers = workers().lock().unwrap_or_else(PoisonError::into_inner);
+
+    if let Ok(job) = queue.try_recv() {
+        return Some(job);
+    }
+
+    queue.close();
+
+    // A worker that was started since, after this one panicked, is kept.
+    if workers.get(endpoints).is_some_and(mpsc::Sender::is_closed) {
+        workers.remove(endpoints);
+    }
+
+    None
+}
+
+async fn send_log(
+    endpoints: &Endpoints,
+    connection: &mut Option<Connection>,
+    log: &Log,
+) -> LoggerResult<()> {
+    let expired = connection
+        .as_ref()
+        .and_then(|connection| connection.failed_over)
+        .is_some_and(|since| since.elapsed() >= endpoints.cooldown);
+
+    let connection = match connection {
+        Some(connection) if !expired => connection,
+        _ => {
+            let (index, client) = transport::connect_first(endpoints).await?;
+
+            connection.insert(Connection {
+                client,
+                failed_over: (index > 0).then(Instant::now),
+            })
+        },
+    };
+
+    let response = runtime::timeout(
+        SEND_TIMEOUT,
+        connection.client.send_log(Request::new(log.clone())),
+    )
+    .await??;
+
+    check_result(response.into_inner())
+}
@@ -0,0 +1,49 @@
+//! Sends logs with the `rt-async-std` feature, which must not start a tokio
+//! runtime for them. Like the unit tests, this needs a CodeCTRL server on
+//! `127.0.0.1:3002`, and is run with:
+//!
+//! ```sh
+//! cargo test --no-default-features --features rt-async-std --test async_std
+//! ```
+
+#![cfg(all(feature = "rt-async-std", not(feature = "disabled")))]
+
+use codectrl::{Logger, LoggerError};
+
+#[test]
+fn async_std_runtime() {
+    let ret = async_std::task::block_on(async {
+        Logger::log_async("Hello, async-std", Some(2), None, None, None).await?;
+        Logger::info_async("Hello again, async-std", Some(2), None, None, None).await?;
+
+        let mut batch = Logger::start_batch().add_log("Batched hello, async-std", None);
+
+        batch.send_async().await?;
+
+        Ok::<_, LoggerError>(())
+    });
+
+    if let Err(e) = ret {
+        panic!("{e}");
+    }
+
+    // From synchronous code, and to a server that isn't there.
+    Logger::log("Hello, async-std from sync code", Some(2), None, None, None).unwrap();
+    assert!(Logger::log("Not sent", None, None, Some("1"), None).is_err());
+    assert!(Logger::check_connection(None, None, None).is_reachable());
+
+    assert!(tokio::runtime::Handle::try_current().is_err());
+
+    // The worker threads of a tokio runtime are named `tokio-runtime-worker`,
+    // and the runtime that is shared by the crate names them
+    // `codectrl-logger`, so neither may exist.
+    #[cfg(target_os = "linux")]
+    for task in std::fs::read_dir("/proc/self/task").unwrap() {
+        let name = std::fs::read_to_string(task.unwrap().path().join("comm")).unwrap();
+
+        assert!(
+            !name.starts_with("tokio-runtime") && !name.starts_with("codectrl-logger"),
+            "a tokio runtime was started: {name}"
+        );
+    }
+}
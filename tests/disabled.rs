@@ -0,0 +1,57 @@
+//! Checks that nothing is logged with the `disabled` feature, which must not
+//! need a CodeCTRL server. Run with:
+//!
+//! ```sh
+//! cargo test --no-default-features --features disabled --test disabled
+//! ```
+
+#![cfg(feature = "disabled")]
+
+use codectrl::{Level, Logger, RateLimit};
+use std::time::Duration;
+
+#[test]
+fn nothing_is_logged() {
+    // Port 1 has no server, so any of these would fail if they were sent.
+    assert!(Logger::log("Not sent", None, None, Some("1"), None).is_ok());
+    assert!(!Logger::log_limited(
+        RateLimit::once(),
+        "Not sent",
+        None,
+        None,
+        Some("1"),
+        None
+    )
+    .unwrap());
+    assert!(!Logger::log_suppressed_summary(None, Some("1"), None).unwrap());
+    assert!(!Logger::check_connection(None, Some("1"), None).is_reachable());
+
+    assert!(!Level::Error.is_enabled());
+    assert!(codectrl::error!("{} not sent", 1).is_ok());
+    assert_eq!(codectrl::dbg!(1 + 1), 2);
+
+    let mut batch = Logger::start_batch()
+        .add_log("Not added", None)
+        .add_log_at(Level::Error, "Not added", None)
+        .add_suppressed_summary();
+
+    assert!(batch.is_empty());
+    assert!(batch.send_reported().unwrap().accepted().is_empty());
+
+    let mut auto_flush = Logger::start_batch().auto_flush().max_logs(1);
+
+    auto_flush.add_log("Not added", None).unwrap();
+    assert!(auto_flush.is_empty());
+
+    // Timers and scopes still work, only their logs aren't sent.
+    let timer = Logger::timer("Not sent").threshold(Duration::ZERO);
+
+    std::thread::sleep(Duration::from_millis(10));
+    assert!(timer.elapsed() >= Duration::from_millis(10));
+    drop(timer);
+
+    let scopes = codectrl::scope("Entered", codectrl::scopes);
+
+    assert_eq!(scopes, ["Entered"]);
+    assert!(codectrl::scopes().is_empty());
+}